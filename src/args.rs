@@ -1,4 +1,10 @@
-use clap::Parser;
+use crate::config::{Config, StorePath};
+use crate::output::Output;
+use crate::sections::{delimiters::Delimiters, parse_options::ParseOptions};
+use crate::snippet::Filter;
+use crate::store::{Format, Layers, Store, DEFAULT_BACKUPS};
+use anyhow::{bail, Result};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -6,23 +12,612 @@ use std::path::PathBuf;
     author,
     version,
     about,
-    long_about = r#"Takes a json file and load it for snippets. The file needs to be valid json, the key will be used for the title, and the body will be used for the snippet. Use '{}' to mark an area as editable this will be the placeholder for replacement.
-Example: {
-        "Title": "Snippet {}",
-        "Another snippet", "Snippet mark new lines \nplaceholder {} can have multiple {} placeholders"
-        }
+    override_usage = "snippset [OPTIONS] [PATHS]...\n       snippset [OPTIONS] <COMMAND>",
+    long_about = r#"Fills in snippets from snippet files and copies them to the clipboard. A snippet file maps titles to snippets with placeholders to fill in, such as:
+
+{
+    "Greeting": "Hello {name}, welcome to {place:Berlin}",
+    "Deploy": "kubectl apply -f {file} -n {namespace|dev,prod}"
+}
+
+Placeholders:
+  {} {name}              Areas to fill in, placeholders sharing a name are filled together
+  {port:8080}            Default value, typing replaces it while Enter accepts it
+  {method|GET,POST}      Choices, Up / Down cycles through them
+  {count!int}            Type that must be valid before finishing: int, float, date, file or regex=<pattern>
+  {name/snake}           Transformed mirror: snake, upper... or regex/<pattern>/<replacement>/<flags>
+  {$DATE} {date:$DATE}   Variables such as $DATE, $USER or $ENV:NAME, filled in when opened
+  {$(whoami)}            Command output, only run with --allow-commands or a store allowing them
+  ${1} ${2:x} ${0}       Tab-stops visited first by number, ${0} last, while a bare $1 is plain text
+  {[ -H "{token}" ]}     Optional group, left out when its placeholders are empty
+  {*id:{id}*, }          Repeat block, Ctrl-n adds a row, Ctrl-d removes one and ', ' separates them
+  {>title}               Another snippet of the same store, its placeholders are filled in too
+  \{ \}                  Literal braces, or pick other delimiters with --delimiters
 
-Enter will move your cursor to the next placeholder if you have multiples, while Esc will move to previous ones."#
+Enter moves to the next placeholder while Esc moves to the previous one.
+
+Snippets:
+  Files can be JSON, TOML or YAML, picked from the extension or --format, or VS Code '.code-snippets' files.
+  A directory is a store where every file is a snippet, subdirectories are categories such as 'docker/run'.
+  Several paths are layered, later ones override snippets with the same title.
+  A snippet can be '{"body": "...", "description": "...", "tags": ["db"], "language": "sql", "aliases": ["rows"]}'.
+
+Running:
+  Skip the list with --snippet <title> or --query <text>, and pick where the result goes with --output.
+  Manage snippets with commands such as 'snippset list', 'snippset rm' or 'snippset render', see 'snippset help <command>'.
+  Default paths and settings are read from ~/.config/snippset/config.toml, see 'snippset config show'."#
 )]
 pub struct Args {
-    /// Path of snippet file to load, file must be valid json with string keys and string values"
-    pub path: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    /// Add snippet to the selected json file using interactive mode. If the selected json file does not exist, a new one in the path will be created.
-    #[arg(short = 'a', long = "add")]
+    /// Paths of snippet files to load, mapping each title to its body or to a table with the body along with a description, aliases, tags and a language, or directories where every file is a snippet. Snippets of later paths override the ones of earlier paths with the same title. Taken from the config file when not given
+    pub paths: Vec<PathBuf>,
+
+    /// Add a snippet to the snippet file or directory, the same as the add command
+    #[arg(short = 'a', long = "add", conflicts_with_all = ["edit", "snippet", "query"])]
     pub add: bool,
 
-    /// Edit snippet in the selected json file
-    #[arg(short = 'e', long = "edit")]
+    /// Edit a snippet of the snippet files or directories, the same as the edit command
+    #[arg(short = 'e', long = "edit", conflicts_with_all = ["snippet", "query"])]
     pub edit: bool,
+
+    #[command(flatten)]
+    pub pick: PickArgs,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Opening and closing placeholder delimiters used by the snippet files, separated by a space such as "${ }" or "<< >>". Replaces the delimiters of every store, "{ }" when neither the config file nor the store sets them
+    #[arg(short = 'd', long = "delimiters", global = true)]
+    pub delimiters: Option<Delimiters>,
+
+    /// Run the commands of placeholders such as '{$(git branch --show-current)}' when a snippet of any path is opened. Only use with snippet files you trust, a single store of the config file can allow them with 'allow-commands = true' instead
+    #[arg(
+        long = "allow-commands",
+        global = true,
+        overrides_with = "no_allow_commands"
+    )]
+    pub allow_commands: bool,
+
+    /// Never run the commands of placeholders, even for the stores of the config file which allow them
+    #[arg(
+        long = "no-allow-commands",
+        global = true,
+        overrides_with = "allow_commands"
+    )]
+    pub no_allow_commands: bool,
+
+    /// Format of the snippet file, picked from its extension when not given: .toml for TOML, .yaml or .yml for YAML and JSON otherwise
+    #[arg(short = 'f', long = "format", global = true)]
+    pub format: Option<Format>,
+
+    /// Number of backups kept when a snippet file is saved, such as 'snippets.json.bak.1' for the most recent one. 0 keeps none, 3 when not given
+    #[arg(long = "backups", global = true)]
+    pub backups: Option<usize>,
+
+    /// Where the filled in snippet goes: clipboard, stdout, file:<path>, append:<path> or exec to run it, clipboard when not given. Without a clipboard, such as over SSH, it's printed instead
+    #[arg(short = 'o', long = "output", global = true)]
+    pub output: Option<Output>,
+
+    /// Config file to read instead of ~/.config/snippset/config.toml
+    #[arg(short = 'c', long = "config", env = "SNIPPSET_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Settings of the config file, used for the options which are not given.
+    #[arg(skip)]
+    pub loaded: Config,
+}
+
+/// Snippet paths given to a command, the ones of the config file are used when none are.
+#[derive(clap::Args, Default)]
+pub struct Paths {
+    /// Paths of snippet files or directories, later paths override earlier ones. Taken from the config file when not given
+    pub paths: Vec<PathBuf>,
+}
+
+/// Picks the snippet to use without listing every snippet.
+#[derive(clap::Args, Default)]
+pub struct PickArgs {
+    /// Title or alias of the snippet to fill in, instead of choosing it from the list
+    #[arg(long = "snippet", conflicts_with = "query")]
+    pub snippet: Option<String>,
+
+    /// Text to find the snippet to fill in with, the best match is used right away while the list is shown filtered by it when several match as well
+    #[arg(short = 'q', long = "query")]
+    pub query: Option<String>,
+}
+
+#[derive(clap::Args, Default)]
+pub struct FilterArgs {
+    /// Only list snippets with this tag, can be given more than once to require every tag
+    #[arg(short = 't', long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Only list snippets written in this language such as "sql" or "bash"
+    #[arg(short = 'l', long = "language")]
+    pub language: Option<String>,
+}
+
+impl FilterArgs {
+    pub fn filter(&self) -> Filter {
+        Filter {
+            tags: self.tags.clone(),
+            language: self.language.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Fill in a snippet, what running snippset without a command does
+    Use(UseArgs),
+    /// Add a snippet, creating the snippet file when it doesn't exist
+    Add(AddArgs),
+    /// Edit a snippet and its metadata
+    Edit(EditArgs),
+    /// Remove a snippet
+    #[command(override_usage = "snippset rm [OPTIONS] [PATHS]... <TITLE>")]
+    Rm(RmArgs),
+    /// Rename a snippet, a title such as 'docker/run' moves it to a category of a directory
+    #[command(override_usage = "snippset mv [OPTIONS] [PATHS]... <FROM> <TO>")]
+    Mv(MvArgs),
+    /// List snippets along with their metadata
+    List(ListArgs),
+    /// Print the body of a snippet
+    #[command(override_usage = "snippset show [OPTIONS] [PATHS]... <TITLE>")]
+    Show(ShowArgs),
+    /// List snippets containing every word of a query
+    #[command(override_usage = "snippset search [OPTIONS] [PATHS]... <QUERY>")]
+    Search(SearchArgs),
+    /// Add the snippets of another snippet file or directory
+    #[command(override_usage = "snippset import [OPTIONS] [PATHS]... <FROM>")]
+    Import(ImportArgs),
+    /// Write snippets to a file or directory, or print them
+    Export(ExportArgs),
+    /// Fill in a snippet with the given values and print it, without prompting
+    #[command(override_usage = "snippset render [OPTIONS] [PATHS]... <TITLE>")]
+    Render(RenderArgs),
+    /// Check snippets for problems such as missing includes or defaults not matching their type
+    Lint(LintArgs),
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(clap::Args, Default)]
+pub struct UseArgs {
+    #[command(flatten)]
+    pub pick: PickArgs,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub paths: Paths,
+}
+
+#[derive(clap::Args, Default)]
+pub struct AddArgs {
+    /// Title of the snippet, asked for when not given
+    #[arg(long = "title")]
+    pub title: Option<String>,
+
+    #[command(flatten)]
+    pub paths: Paths,
+}
+
+#[derive(clap::Args, Default)]
+pub struct EditArgs {
+    /// Title of the snippet to edit, chosen from a list when not given
+    #[arg(long = "title")]
+    pub title: Option<String>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub paths: Paths,
+}
+
+#[derive(clap::Args)]
+pub struct RmArgs {
+    /// Paths of snippet files or directories followed by the title of the snippet to remove, the paths are taken from the config file when only the title is given
+    #[arg(value_name = "PATHS... TITLE", required = true)]
+    pub targets: Vec<String>,
+
+    /// Remove without asking
+    #[arg(short = 'y', long = "yes")]
+    pub yes: bool,
+}
+
+#[derive(clap::Args)]
+pub struct MvArgs {
+    /// Paths of snippet files or directories followed by the title of the snippet to rename and its new title, the paths are taken from the config file when only the titles are given
+    #[arg(value_names = ["PATHS... FROM", "TO"], num_args = 2.., required = true)]
+    pub targets: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct ListArgs {
+    /// Only print the titles, one per line
+    #[arg(long = "titles")]
+    pub titles: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub paths: Paths,
+}
+
+#[derive(clap::Args)]
+pub struct ShowArgs {
+    /// Paths of snippet files or directories followed by the title of the snippet to print, the paths are taken from the config file when only the title is given
+    #[arg(value_name = "PATHS... TITLE", required = true)]
+    pub targets: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct SearchArgs {
+    /// Paths of snippet files or directories followed by the words to look for in the title, body and metadata of snippets, ignoring case. The paths are taken from the config file when only the words are given
+    #[arg(value_name = "PATHS... QUERY", required = true)]
+    pub targets: Vec<String>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(clap::Args)]
+pub struct ImportArgs {
+    /// Paths of snippet files or directories followed by the snippet file or directory to import, in any of the formats. The paths are taken from the config file when only the one to import is given
+    #[arg(value_name = "PATHS... FROM", required = true)]
+    pub targets: Vec<String>,
+
+    /// Replace snippets with the same title instead of skipping them
+    #[arg(long = "overwrite")]
+    pub overwrite: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    /// File or directory to write the snippets to, they are printed when not given
+    #[arg(long = "to")]
+    pub to: Option<PathBuf>,
+
+    /// Format to export as, picked from the extension of --to when not given
+    #[arg(long = "as")]
+    pub format: Option<Format>,
+
+    /// Add the snippets to the file or directory when it already exists, replacing the ones with the same title
+    #[arg(long = "force")]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub paths: Paths,
+}
+
+#[derive(clap::Args)]
+pub struct RenderArgs {
+    /// Paths of snippet files or directories followed by the title of the snippet to fill in, the paths are taken from the config file when only the title is given
+    #[arg(value_name = "PATHS... TITLE", required = true)]
+    pub targets: Vec<String>,
+
+    /// Value of a placeholder such as 'name=foo', '2=bar' for the tab-stop '${2}' or the second one filled in when there is no such tab-stop, or '#3=baz' for the third one filled in
+    #[arg(short = 's', long = "set", value_name = "NAME=VALUE", value_parser = parse_value)]
+    pub values: Vec<(String, String)>,
+
+    /// JSON object of placeholder values such as '{"name": "foo", "2": "bar"}' read from a file, or from stdin with '-'. Values of --set replace them
+    #[arg(long = "values", value_name = "FILE")]
+    pub values_file: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct LintArgs {
+    #[command(flatten)]
+    pub paths: Paths,
+}
+
+impl RmArgs {
+    pub fn title(&self) -> &str {
+        trailing(&self.targets, 1)[0]
+    }
+
+    pub fn paths(&self) -> Paths {
+        leading_paths(&self.targets, 1)
+    }
+}
+
+impl MvArgs {
+    /// The title of the snippet to rename and its new title.
+    pub fn titles(&self) -> (&str, &str) {
+        let titles = trailing(&self.targets, 2);
+        (titles[0], titles[1])
+    }
+
+    pub fn paths(&self) -> Paths {
+        leading_paths(&self.targets, 2)
+    }
+}
+
+impl ShowArgs {
+    pub fn title(&self) -> &str {
+        trailing(&self.targets, 1)[0]
+    }
+
+    pub fn paths(&self) -> Paths {
+        leading_paths(&self.targets, 1)
+    }
+}
+
+impl SearchArgs {
+    pub fn query(&self) -> &str {
+        trailing(&self.targets, 1)[0]
+    }
+
+    pub fn paths(&self) -> Paths {
+        leading_paths(&self.targets, 1)
+    }
+}
+
+impl ImportArgs {
+    pub fn from(&self) -> PathBuf {
+        PathBuf::from(trailing(&self.targets, 1)[0])
+    }
+
+    pub fn paths(&self) -> Paths {
+        leading_paths(&self.targets, 1)
+    }
+}
+
+impl RenderArgs {
+    pub fn title(&self) -> &str {
+        trailing(&self.targets, 1)[0]
+    }
+
+    pub fn paths(&self) -> Paths {
+        leading_paths(&self.targets, 1)
+    }
+}
+
+/// The last `count` arguments of a command, given after its paths. Clap can't take
+/// optional paths before a required argument, so both are parsed as one list.
+fn trailing(targets: &[String], count: usize) -> Vec<&str> {
+    let start = targets.len().saturating_sub(count);
+    targets[start..].iter().map(String::as_str).collect()
+}
+
+/// The paths given before the last `count` arguments of a command.
+fn leading_paths(targets: &[String], count: usize) -> Paths {
+    let end = targets.len().saturating_sub(count);
+    Paths {
+        paths: targets[..end].iter().map(PathBuf::from).collect(),
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the configuration in effect, the config file along with the options given
+    Show,
+}
+
+impl Args {
+    pub fn load_config(&mut self) -> Result<()> {
+        self.loaded = Config::load(self.config.as_deref())?;
+        Ok(())
+    }
+
+    /// Exits with an error when options of running snippset without a command are given
+    /// along with one, which would be left unused otherwise.
+    pub fn check_command(&self) {
+        let options = self.add
+            || self.edit
+            || self.pick.snippet.is_some()
+            || self.pick.query.is_some()
+            || !self.filter.tags.is_empty()
+            || self.filter.language.is_some();
+        if self.command.is_some() && options {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--add, --edit, --snippet, --query, --tag and --language can't be given before a command",
+                )
+                .exit()
+        }
+    }
+
+    /// The command to run, running snippset without one uses a snippet or adds or edits
+    /// one with `--add` and `--edit`.
+    pub fn take_command(&mut self) -> Command {
+        if let Some(command) = self.command.take() {
+            return command;
+        }
+
+        let paths = Paths {
+            paths: std::mem::take(&mut self.paths),
+        };
+        let filter = std::mem::take(&mut self.filter);
+        let pick = std::mem::take(&mut self.pick);
+        match (self.add, self.edit) {
+            (true, _) => Command::Add(AddArgs { paths, title: None }),
+            (_, true) => Command::Edit(EditArgs {
+                paths,
+                filter,
+                title: None,
+            }),
+            _ => Command::Use(UseArgs {
+                pick,
+                paths,
+                filter,
+            }),
+        }
+    }
+
+    /// The configuration in effect, options given on the command line replace the ones
+    /// of the config file.
+    pub fn effective(&self) -> Config {
+        let paths = self
+            .paths(&Paths::default())
+            .into_iter()
+            .map(|store| StorePath {
+                allow_commands: self.allow_commands(&store),
+                ..store
+            })
+            .collect();
+        Config {
+            paths,
+            output: Some(self.output()),
+            delimiters: Some(self.delimiters()),
+            format: self.format.or(self.loaded.format),
+            backups: Some(self.backups()),
+            theme: self.loaded.theme,
+            keys: self.loaded.keys.clone(),
+        }
+    }
+
+    /// The given paths along with their settings from the config file, or the paths of
+    /// the config file when none are given.
+    pub fn paths(&self, given: &Paths) -> Vec<StorePath> {
+        match given.paths.is_empty() {
+            true => self.loaded.paths.clone(),
+            false => given
+                .paths
+                .iter()
+                .map(|path| match self.loaded.store(path) {
+                    Some(store) => store.clone(),
+                    None => StorePath::new(path.clone()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn delimiters(&self) -> Delimiters {
+        self.store_delimiters(None)
+    }
+
+    /// Delimiters of a store, the ones given on the command line replace the ones of the
+    /// store which replace the ones of the config file.
+    pub fn store_delimiters(&self, store: Option<&Delimiters>) -> Delimiters {
+        self.delimiters
+            .as_ref()
+            .or(store)
+            .or(self.loaded.delimiters.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn output(&self) -> Output {
+        self.output
+            .clone()
+            .or_else(|| self.loaded.output.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn backups(&self) -> usize {
+        self.backups
+            .or(self.loaded.backups)
+            .unwrap_or(DEFAULT_BACKUPS)
+    }
+
+    pub fn layers(&self, paths: &Paths) -> Result<Layers> {
+        let paths = self.paths(paths);
+        if paths.is_empty() {
+            bail!("No snippet path given, pass one or set 'paths' in the config file")
+        }
+
+        let format = self.format.or(self.loaded.format);
+        let stores = paths
+            .iter()
+            .map(|store| {
+                Store::new(&store.path, format, self.backups())
+                    .with_delimiters(self.store_delimiters(store.delimiters.as_ref()))
+                    .with_commands(self.allow_commands(store))
+            })
+            .collect();
+        Ok(Layers::new(stores))
+    }
+
+    /// Whether the commands of a store are run, --no-allow-commands turns them off for
+    /// every store while --allow-commands turns them on.
+    pub fn allow_commands(&self, store: &StorePath) -> bool {
+        !self.no_allow_commands && (self.allow_commands || store.allow_commands)
+    }
+
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            delimiters: self.delimiters(),
+            ..ParseOptions::default()
+        }
+    }
+}
+
+/// A placeholder value given as `name=value`.
+fn parse_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(format!("{s:?} is not written as name=value")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Args, Command};
+    use crate::config::StorePath;
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    #[test]
+    fn paths_come_before_titles() {
+        let args = [
+            "snippset", "-c", "c.toml", "render", "a.json", "greet", "-s", "n=v",
+        ];
+        let args = Args::try_parse_from(args).unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("c.toml")));
+        let Some(Command::Render(render)) = args.command else {
+            panic!("not parsed as render")
+        };
+        assert_eq!(render.title(), "greet");
+        assert_eq!(render.paths().paths, vec![PathBuf::from("a.json")]);
+        assert_eq!(render.values, vec![("n".to_owned(), "v".to_owned())]);
+
+        let args = Args::try_parse_from(["snippset", "rm", "a.json", "b.json", "greet"]).unwrap();
+        let Some(Command::Rm(rm)) = args.command else {
+            panic!("not parsed as rm")
+        };
+        assert_eq!(rm.title(), "greet");
+        assert_eq!(rm.paths().paths.len(), 2);
+
+        let args = Args::try_parse_from(["snippset", "mv", "greet", "hello"]).unwrap();
+        let Some(Command::Mv(mv)) = args.command else {
+            panic!("not parsed as mv")
+        };
+        assert_eq!(mv.titles(), ("greet", "hello"));
+        assert!(mv.paths().paths.is_empty());
+        assert!(Args::try_parse_from(["snippset", "mv", "greet"]).is_err());
+    }
+
+    #[test]
+    fn commands_are_allowed_per_store() {
+        let trusted = StorePath {
+            allow_commands: true,
+            ..StorePath::new(PathBuf::from("mine.json"))
+        };
+        let other = StorePath::new(PathBuf::from("team.json"));
+        let allowed = |args: &[&str]| {
+            let args = Args::try_parse_from([&["snippset"], args].concat()).unwrap();
+            (args.allow_commands(&trusted), args.allow_commands(&other))
+        };
+        assert_eq!(allowed(&[]), (true, false));
+        assert_eq!(allowed(&["--allow-commands"]), (true, true));
+        assert_eq!(allowed(&["--no-allow-commands"]), (false, false));
+        assert_eq!(
+            allowed(&["--allow-commands", "--no-allow-commands"]),
+            (false, false)
+        );
+    }
 }
@@ -0,0 +1,427 @@
+use crate::args::{
+    Args, ExportArgs, ImportArgs, LintArgs, ListArgs, MvArgs, RenderArgs, RmArgs, SearchArgs,
+    ShowArgs,
+};
+use crate::engine_modes::{choose_layer, find, names, prepare};
+use crate::includes;
+use crate::sections::parse_options::{ParseOptions, Syntax};
+use crate::sections::section_manager::SectionManager;
+use crate::snippet::Listed;
+use crate::store::{Format, Layers, Merged, Store, DEFAULT_INDENT};
+use crate::Snippets;
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use inquire::Confirm;
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+pub fn remove(config: &Args, args: RmArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths())?;
+    let merged = layers.load()?;
+    let title = find(&merged.snippets, args.title())?;
+    let index = merged.source(title);
+    let name = layers.name(index);
+
+    if !args.yes {
+        let remove = Confirm::new(&format!("Remove {title:?} from {name}?"))
+            .with_default(false)
+            .prompt()?;
+        if !remove {
+            println!("Nothing was removed");
+            return Ok(());
+        }
+    }
+
+    change(&mut layers, index, |store, snippets| {
+        store.remove(snippets, title)
+    })?;
+    println!("Removed {title:?} from {name}");
+    Ok(())
+}
+
+pub fn rename(config: &Args, args: MvArgs) -> Result<()> {
+    let (from, to) = args.titles();
+    let mut layers = config.layers(&args.paths())?;
+    let merged = layers.load()?;
+    let title = find(&merged.snippets, from)?;
+    if merged.snippets.contains_key(to) {
+        bail!("There already is a snippet titled {to:?}")
+    }
+
+    change(&mut layers, merged.source(title), |store, snippets| {
+        let Some(snippet) = snippets.get(title).cloned() else {
+            bail!("There is no snippet titled {title:?}, it was removed by someone else meanwhile")
+        };
+        store.save(snippets, to.to_owned(), snippet, Some(title))
+    })?;
+    println!("Renamed {title:?} to {to:?}");
+    Ok(())
+}
+
+pub fn list(config: &Args, args: ListArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths)?;
+    let merged = layers.load()?;
+    let filter = args.filter.filter();
+    let titles = merged
+        .snippets
+        .iter()
+        .filter(|(_, snippet)| filter.keeps(snippet))
+        .map(|(title, _)| title);
+
+    match args.titles {
+        true => titles.for_each(|title| println!("{title}")),
+        false => print_listed(&layers, &merged, titles),
+    }
+    Ok(())
+}
+
+pub fn show(config: &Args, args: ShowArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths())?;
+    let merged = layers.load()?;
+    let title = find(&merged.snippets, args.title())?;
+    println!("{}", merged.snippets[title].body);
+    Ok(())
+}
+
+pub fn search(config: &Args, args: SearchArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths())?;
+    let merged = layers.load()?;
+    let filter = args.filter.filter();
+    let titles = merged
+        .snippets
+        .iter()
+        .filter(|(title, snippet)| filter.keeps(snippet) && snippet.matches(title, args.query()))
+        .map(|(title, _)| title);
+
+    print_listed(&layers, &merged, titles);
+    Ok(())
+}
+
+pub fn import(config: &Args, args: ImportArgs) -> Result<()> {
+    let from = args.from();
+    let (imported, syntax) = Store::new(&from, None, 0).load()?;
+    if syntax == Syntax::TextMate {
+        bail!(
+            "{from:?} is a VS Code snippet file written in another syntax, it can be used as one of the paths instead"
+        )
+    }
+
+    let mut layers = config.layers(&args.paths())?;
+    if layers.exists() {
+        layers.load()?;
+    }
+    let index = choose_layer(&layers, None)?;
+    let name = layers.name(index);
+
+    let store = layers.store_mut(index);
+    let _lock = store.lock()?;
+    let mut snippets = match store.exists() {
+        true => store.load_editable()?,
+        false => Snippets::new(),
+    };
+    let total = imported.len();
+    let added: Snippets = imported
+        .into_iter()
+        .filter(|(title, _)| args.overwrite || !snippets.contains_key(title))
+        .collect();
+    let count = added.len();
+    store.save_all(&mut snippets, added)?;
+
+    println!(
+        "Imported {count} snippets into {name}, {} already there were skipped",
+        total - count
+    );
+    Ok(())
+}
+
+pub fn export(config: &Args, args: ExportArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths)?;
+    let merged = layers.load()?;
+    let filter = args.filter.filter();
+    // Snippets of VS Code files would not be read with the same syntax
+    let snippets: Snippets = merged
+        .snippets
+        .iter()
+        .filter(|(title, snippet)| {
+            filter.keeps(snippet) && merged.syntax(title) == Syntax::Snippset
+        })
+        .map(|(title, snippet)| (title.clone(), snippet.clone()))
+        .collect();
+
+    let Some(to) = &args.to else {
+        let format = args.format.unwrap_or(Format::Json);
+        print!("{}", format.write(&snippets, DEFAULT_INDENT)?);
+        return Ok(());
+    };
+
+    if to.exists() && !args.force {
+        bail!("{to:?} already exists, use --force to replace its snippets with the same title")
+    }
+
+    let mut store = Store::new(to, args.format, config.backups());
+    let _lock = store.lock()?;
+    let mut existing = match store.exists() {
+        true => store.load_editable()?,
+        false => Snippets::new(),
+    };
+    let count = snippets.len();
+    store.save_all(&mut existing, snippets)?;
+    println!("Exported {count} snippets to {to:?}");
+    Ok(())
+}
+
+pub fn render(config: &Args, args: RenderArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths())?;
+    let merged = layers.load()?;
+    let title = find(&merged.snippets, args.title())?;
+    let (snippet, options) = prepare(config, &merged, title)?;
+    let mut manager = SectionManager::with_options(&snippet, &options);
+
+    let values = given_values(args.values_file.as_deref(), args.values, io::stdin())?;
+    for (key, value) in values {
+        manager.set(&key, &value)?;
+    }
+
+    let unfilled = manager.unfilled();
+    if !unfilled.is_empty() {
+        bail!(
+            "Placeholders without a valid value: {}",
+            unfilled.join(", ")
+        )
+    }
+    println!("{}", manager.text());
+    Ok(())
+}
+
+pub fn lint(config: &Args, args: LintArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths)?;
+    let merged = layers.load()?;
+    let mut count = 0;
+    for title in merged.snippets.keys() {
+        if merged.syntax(title) != Syntax::Snippset {
+            continue;
+        }
+
+        let options = ParseOptions {
+            allow_commands: false,
+            delimiters: merged.delimiters(title).clone(),
+            ..config.parse_options()
+        };
+        for problem in problems(title, &merged.snippets, merged.store(title), &options) {
+            println!("{title}: {problem}");
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        bail!("Found {count} problems")
+    }
+    println!("No problems found in {} snippets", merged.snippets.len());
+    Ok(())
+}
+
+pub fn show_config(config: &Args) -> Result<()> {
+    print!("{}", toml::to_string(&config.effective())?);
+    Ok(())
+}
+
+/// Changes the snippets of a layer while holding its lock.
+fn change(
+    layers: &mut Layers,
+    index: usize,
+    update: impl FnOnce(&mut Store, &mut Snippets) -> Result<()>,
+) -> Result<()> {
+    if !layers.writable().contains(&index) {
+        bail!("{} can't be changed", layers.name(index))
+    }
+
+    let store = layers.store_mut(index);
+    let _lock = store.lock()?;
+    let mut snippets = store.load_editable()?;
+    update(store, &mut snippets)
+}
+
+fn print_listed<'a>(layers: &Layers, merged: &Merged, titles: impl Iterator<Item = &'a String>) {
+    let names = names(layers);
+    for title in titles {
+        let listed = Listed {
+            title,
+            snippet: &merged.snippets[title],
+            source: (names.len() > 1).then(|| names[merged.source(title)].as_str()),
+        };
+        println!("{listed}");
+    }
+}
+
+/// The values of the `--values` file followed by the ones of `--set` which replace them.
+/// Stdin is only read when the file is `-`, so a script piping into a loop around render
+/// keeps its input and an inherited pipe which is never closed doesn't block.
+fn given_values(
+    file: Option<&Path>,
+    set: Vec<(String, String)>,
+    mut stdin: impl Read,
+) -> Result<Vec<(String, String)>> {
+    let input = match file {
+        None => String::new(),
+        Some(path) if path == Path::new("-") => {
+            let mut input = String::new();
+            stdin
+                .read_to_string(&mut input)
+                .context("The values could not be read from stdin")?;
+            input
+        }
+        Some(path) => {
+            fs::read_to_string(path).with_context(|| format!("{path:?} could not be read"))?
+        }
+    };
+
+    let mut values = read_values(&input)?;
+    values.extend(set);
+    Ok(values)
+}
+
+/// Placeholder values of a JSON object such as `{"name": "foo", "2": 8080}`, empty input
+/// has none.
+fn read_values(input: &str) -> Result<Vec<(String, String)>> {
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let object: IndexMap<String, Value> =
+        serde_json::from_str(input).context("The values on stdin are not a JSON object")?;
+    object
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => Ok((key, value)),
+            Value::Number(_) | Value::Bool(_) => Ok((key, value.to_string())),
+            _ => bail!("The value of {key:?} is not a string, a number or a boolean"),
+        })
+        .collect()
+}
+
+/// Problems of a snippet which would only show once it is used, its includes are taken
+/// from the snippets of its own store.
+fn problems(
+    title: &str,
+    snippets: &Snippets,
+    store: &Snippets,
+    options: &ParseOptions,
+) -> Vec<String> {
+    let snippet = &snippets[title];
+    let mut problems = Vec::new();
+    if snippet.body.trim().is_empty() {
+        problems.push("the snippet is empty".to_owned());
+    }
+
+    for alias in &snippet.aliases {
+        if snippets.contains_key(alias) {
+            problems.push(format!(
+                "the alias {alias:?} is the title of another snippet"
+            ));
+        }
+        let other = snippets
+            .iter()
+            .find(|(t, s)| *t != title && s.aliases.contains(alias));
+        if let Some((other, _)) = other {
+            problems.push(format!("the alias {alias:?} is also an alias of {other:?}"));
+        }
+    }
+
+    let body = match includes::expand(title, store, &options.delimiters) {
+        Ok(body) => body,
+        Err(e) => {
+            problems.push(e.to_string());
+            return problems;
+        }
+    };
+
+    let manager = SectionManager::with_options(&body, options);
+    for section in &manager.sections {
+        let default = section
+            .suffix
+            .as_ref()
+            .map(|e| e.chars().into_iter().collect::<String>())
+            .unwrap_or_default();
+        if default.is_empty() {
+            continue;
+        }
+        if let Some(error) = section.validation_error() {
+            let name = section.name.as_deref().unwrap_or("a placeholder");
+            problems.push(format!(
+                "the default {default:?} of {name} is invalid, {error}"
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use super::{given_values, problems, read_values};
+    use crate::sections::parse_options::ParseOptions;
+    use crate::snippet::Snippet;
+    use crate::Snippets;
+    use std::io::{self, Read};
+    use std::path::Path;
+
+    #[test]
+    fn finds_problems() {
+        let mut snippets: Snippets = [
+            ("port", "listen {port!int:http}"),
+            ("script", "{>missing}\necho"),
+            ("empty", " "),
+            ("fine", "ls {path:.}"),
+        ]
+        .into_iter()
+        .map(|(title, body)| (title.to_owned(), Snippet::new(body)))
+        .collect();
+        snippets["fine"].aliases = vec!["port".to_owned()];
+
+        let options = ParseOptions::default();
+        let problems = |title| problems(title, &snippets, &snippets, &options);
+        assert_eq!(problems("port").len(), 1);
+        assert!(problems("port")[0].contains("\"http\" of port"));
+        assert_eq!(problems("script").len(), 1);
+        assert_eq!(problems("empty"), vec!["the snippet is empty"]);
+        assert_eq!(
+            problems("fine"),
+            vec!["the alias \"port\" is the title of another snippet"]
+        );
+    }
+
+    #[test]
+    fn reads_stdin_only_when_asked() {
+        /// Stdin of a script which is not a terminal and must be left alone.
+        struct Untouched;
+        impl Read for Untouched {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                panic!("stdin was read")
+            }
+        }
+
+        let set = vec![("name".to_owned(), "ada".to_owned())];
+        assert_eq!(given_values(None, set.clone(), Untouched).unwrap(), set);
+
+        let stdin = r#"{"name": "bob", "port": 80}"#.as_bytes();
+        let values = given_values(Some(Path::new("-")), set, stdin).unwrap();
+        let expected = [("name", "bob"), ("port", "80"), ("name", "ada")]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn reads_values() {
+        let values = read_values(r#"{"name": "ada", "2": 8080, "debug": true}"#).unwrap();
+        let expected = [("name", "ada"), ("2", "8080"), ("debug", "true")]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        assert_eq!(values, expected);
+        assert!(read_values(" \n").unwrap().is_empty());
+        assert!(read_values(r#"["ada"]"#).is_err());
+        assert!(read_values(r#"{"name": null}"#).is_err());
+    }
+}
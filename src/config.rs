@@ -0,0 +1,263 @@
+use crate::keys::Keys;
+use crate::output::Output;
+use crate::sections::delimiters::Delimiters;
+use crate::store::Format;
+use crate::theme::Theme;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Settings read from the config file, the options given on the command line take
+/// precedence over them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Snippet paths used when none are given, layered in order.
+    pub paths: Vec<StorePath>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Output>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delimiters: Option<Delimiters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backups: Option<usize>,
+    pub theme: Theme,
+    pub keys: Keys,
+}
+
+impl Config {
+    /// Reads the config file at the path, or at the default path when none is given. A
+    /// missing default file is the same as an empty one.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let (path, given) = match path {
+            Some(path) => (path.to_owned(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        if !given && !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("{path:?} could not be read"))?;
+        let mut config: Config =
+            toml::from_str(&content).with_context(|| format!("{path:?} is not a valid config"))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for store in &mut config.paths {
+            store.path = resolve(&store.path, directory);
+        }
+        Ok(config)
+    }
+
+    /// Settings of the store at the path when the config file lists it.
+    pub fn store(&self, path: &Path) -> Option<&StorePath> {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+        self.paths
+            .iter()
+            .find(|s| std::path::absolute(&s.path).is_ok_and(|p| p == path))
+    }
+}
+
+/// A snippet path of the config file, written as the path alone or as a table along with
+/// settings of that store such as `{ path = "templates.json", delimiters = "<< >>" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "Written", into = "Written")]
+pub struct StorePath {
+    pub path: PathBuf,
+    /// Placeholder delimiters of every snippet of the store, the global ones when not set.
+    pub delimiters: Option<Delimiters>,
+    /// Whether the commands of the store's placeholders such as `{$(whoami)}` are run, so
+    /// trusting one store never runs the commands of another.
+    pub allow_commands: bool,
+}
+
+impl StorePath {
+    pub fn new(path: PathBuf) -> Self {
+        StorePath {
+            path,
+            delimiters: None,
+            allow_commands: false,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum Written {
+    Path(PathBuf),
+    Table(StoreTable),
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct StoreTable {
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delimiters: Option<Delimiters>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    allow_commands: bool,
+}
+
+impl From<Written> for StorePath {
+    fn from(written: Written) -> Self {
+        match written {
+            Written::Path(path) => StorePath::new(path),
+            Written::Table(StoreTable {
+                path,
+                delimiters,
+                allow_commands,
+            }) => StorePath {
+                path,
+                delimiters,
+                allow_commands,
+            },
+        }
+    }
+}
+
+impl From<StorePath> for Written {
+    fn from(store: StorePath) -> Self {
+        match (store.delimiters, store.allow_commands) {
+            (None, false) => Written::Path(store.path),
+            (delimiters, allow_commands) => Written::Table(StoreTable {
+                path: store.path,
+                delimiters,
+                allow_commands,
+            }),
+        }
+    }
+}
+
+/// `config.toml` in the `snippset` directory of the user's config directory, such as
+/// `~/.config/snippset/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| match cfg!(windows) {
+            true => env::var_os("APPDATA").map(PathBuf::from),
+            false => env::var_os("HOME").map(|home| Path::new(&home).join(".config")),
+        })?;
+    Some(dir.join("snippset").join("config.toml"))
+}
+
+/// Paths written such as `~/snippets` are in the home directory, while relative paths
+/// are relative to the directory of the config file so they don't depend on where
+/// snippset is run from.
+fn resolve(path: &Path, directory: &Path) -> PathBuf {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"));
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => directory.join(path),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use crate::output::Output;
+    use ratatui::style::Color;
+    use std::{fs, path::Path};
+    use tempfile::TempDir;
+
+    #[test]
+    fn reads_config_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let content = r#"
+paths = ["/etc/snippset", "team.json"]
+output = "stdout"
+delimiters = "<< >>"
+
+[theme]
+placeholder = "light-blue"
+
+[keys]
+next = "tab"
+"#;
+        fs::write(&path, content).unwrap();
+
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.paths.len(), 2);
+        assert_eq!(config.output, Some(Output::Stdout));
+        assert_eq!(config.delimiters.unwrap().open, vec!['<', '<']);
+        assert_eq!(config.theme.placeholder, Color::LightBlue);
+        assert_eq!(config.theme.invalid, Color::Red);
+        assert_eq!(config.keys.next.to_string(), "Tab");
+        assert_eq!(config.keys.previous.to_string(), "Esc");
+    }
+
+    #[test]
+    fn reads_settings_of_stores() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let content = r#"
+paths = [
+    "snippets.json",
+    { path = "templates.json", delimiters = "<< >>" },
+    { path = "scripts.json", allow-commands = true },
+]
+"#;
+        fs::write(&path, content).unwrap();
+
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.paths[0].delimiters, None);
+        assert!(!config.paths[0].allow_commands);
+        let templates = config.store(&dir.path().join("templates.json")).unwrap();
+        assert_eq!(templates.delimiters.as_ref().unwrap().open, vec!['<', '<']);
+        assert!(!templates.allow_commands);
+        assert!(config.paths[2].allow_commands);
+
+        let shown = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&shown).unwrap(), config);
+        fs::write(&path, "paths = [{ path = \"a.json\", colour = \"red\" }]").unwrap();
+        assert!(Config::load(Some(&path)).is_err());
+    }
+
+    #[test]
+    fn resolves_paths_against_the_config_directory() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            r#"paths = ["team.json", "../shared", "/etc/snippset"]"#,
+        )
+        .unwrap();
+
+        let config = Config::load(Some(&path)).unwrap();
+        let paths = config.paths.iter().map(|s| &s.path).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                &dir.path().join("team.json"),
+                &dir.path().join("../shared"),
+                &Path::new("/etc/snippset").to_owned()
+            ]
+        );
+        assert!(config.store(&dir.path().join("team.json")).is_some());
+        assert!(config.store(Path::new("team.json")).is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "path = \"snippets.json\"").unwrap();
+        assert!(Config::load(Some(&path)).is_err());
+        assert!(Config::load(Some(&dir.path().join("missing.toml"))).is_err());
+    }
+
+    #[test]
+    fn shows_as_read() {
+        let config = Config::default();
+        let shown = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&shown).unwrap(), config);
+    }
+}
@@ -1,68 +1,356 @@
+use crate::args::{AddArgs, Args, EditArgs, UseArgs};
+use crate::includes;
+use crate::sections::parse_options::{ParseOptions, Syntax};
+use crate::snippet::{Filter, Listed, Snippet};
 use crate::snippet_engine::SnippetEngine;
+use crate::store::{Layers, Merged, Store};
 use crate::Snippets;
-use arboard::Clipboard;
-use inquire::{Select, Text};
-use std::path::{Path, PathBuf};
-use std::{fs::File, io::BufReader};
-use anyhow::{bail, Context, Result};
-
-pub fn start_editing_engine(path: PathBuf) -> Result<()> {
-    let map: Snippets = get_snippets_from_file(&path)?;
-    let key = Select::new("Choose snippet", map.keys().collect()).prompt()?;
-
-    let snippet = match map.get(key) {
-        Some(sn) => sn,
-        None => bail!("Couldn't find snippet for key {key}")
+use anyhow::{bail, Result};
+use inquire::{Confirm, Select, Text};
+
+/// Fills in a snippet, returning it so it's written once the terminal is left.
+pub fn start_editing_engine(config: &Args, args: UseArgs) -> Result<String> {
+    let mut layers = config.layers(&args.paths)?;
+    let merged = layers.load()?;
+    let filter = args.filter.filter();
+    let key = match &args.pick.snippet {
+        Some(title) => find(&merged.snippets, title)?,
+        None => choose(
+            "Choose snippet",
+            &merged,
+            &names(&layers),
+            &filter,
+            args.pick.query.as_deref(),
+        )?,
+    };
+
+    let (snippet, options) = prepare(config, &merged, key)?;
+    let mut snippet_engine = SnippetEngine::with_options(key, &snippet, &options)
+        .with_theme(config.loaded.theme)
+        .with_keys(config.loaded.keys.clone());
+    snippet_engine.start()
+}
+
+/// The body of a snippet with its includes expanded, along with the options to parse it.
+pub fn prepare(config: &Args, merged: &Merged, key: &str) -> Result<(String, ParseOptions)> {
+    let syntax = merged.syntax(key);
+    let snippet = match syntax {
+        Syntax::Snippset => includes::expand(key, merged.store(key), merged.delimiters(key))?,
+        Syntax::TextMate => merged.snippets[key].body.clone(),
+    };
+
+    let options = ParseOptions {
+        syntax,
+        delimiters: merged.delimiters(key).clone(),
+        allow_commands: merged.allows_commands(key),
+        ..config.parse_options()
+    };
+    Ok((snippet, options))
+}
+
+pub fn add_to_file(config: &Args, args: AddArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths)?;
+    if layers.exists() {
+        layers.load()?;
+    }
+    let index = choose_layer(&layers, None)?;
+    let store = layers.store_mut(index);
+
+    // Only a missing file starts empty, anything else could be overwritten
+    let map: Snippets = match store.exists() {
+        true => store.load_editable()?,
+        false => Snippets::new(),
+    };
+    let title = match args.title {
+        Some(title) => title,
+        None => Text::new("Title: ").prompt()?,
+    };
+    let snippet = prompt_snippet(&Snippet::default())?;
+
+    // Adding a snippet with the title of an existing one replaces it in its place
+    let previous = map.contains_key(&title).then(|| title.clone());
+    save(store, map, title, snippet, previous.as_deref())
+}
+
+pub fn edit_file(config: &Args, args: EditArgs) -> Result<()> {
+    let mut layers = config.layers(&args.paths)?;
+    let merged = layers.load()?;
+
+    let key = match &args.title {
+        Some(title) => find(&merged.snippets, title)?,
+        None => choose(
+            "Choose snippet to edit",
+            &merged,
+            &names(&layers),
+            &args.filter.filter(),
+            None,
+        )?,
     };
 
-    let mut snippet_engine = SnippetEngine::new(key, snippet);
-    let text = snippet_engine.start()?;
+    // A snippet of a layer which can't be changed is overridden in another one
+    let index = choose_layer(&layers, Some(merged.source(key)))?;
+    let store = layers.store_mut(index);
+    let map: Snippets = match store.exists() {
+        true => store.load_editable()?,
+        false => Snippets::new(),
+    };
+    let previous = map.contains_key(key).then_some(key.as_str());
+
+    let title = Text::new("Title: ").with_initial_value(key).prompt()?;
+
+    let snippet = prompt_snippet(&merged.snippets[key])?;
+
+    save(store, map, title, snippet, previous)
+}
+
+/// Saves a snippet while holding the lock of the store. When someone else changed the
+/// snippets since they were read the change can be merged into theirs, which reads them
+/// again and applies only this snippet on top.
+fn save(
+    store: &mut Store,
+    mut map: Snippets,
+    title: String,
+    snippet: Snippet,
+    previous: Option<&str>,
+) -> Result<()> {
+    let _lock = store.lock()?;
+    if store.changed()? {
+        let merge = Confirm::new(
+            "The snippets were changed by someone else, merge this snippet into them?",
+        )
+        .with_default(true)
+        .with_help_message("Otherwise nothing is saved")
+        .prompt()?;
+        if !merge {
+            bail!("Nothing was saved, the snippets were changed by someone else")
+        }
+
+        map = store.load_editable()?;
+        if previous != Some(title.as_str()) && map.contains_key(&title) {
+            bail!("Nothing was saved, {title:?} was added by someone else meanwhile")
+        }
+    }
+
+    store.save(&mut map, title, snippet, previous)
+}
+
+/// Lets the user pick one of the snippets kept by the filter, listed in the order they
+/// are written in the store along with their metadata and the layer they come from.
+/// A query picks the snippet it matches best right away, the list starts out filtered by
+/// it when several match as well.
+fn choose<'a>(
+    message: &str,
+    merged: &'a Merged,
+    names: &[String],
+    filter: &Filter,
+    query: Option<&str>,
+) -> Result<&'a String> {
+    let titles = merged
+        .snippets
+        .iter()
+        .filter(|(_, snippet)| filter.keeps(snippet))
+        .map(|(title, _)| title)
+        .collect::<Vec<_>>();
+    if titles.is_empty() {
+        bail!("No snippet matches the given tags and language")
+    }
 
-    let mut clipboard = Clipboard::new()?;
-    clipboard.set_text(text)?;
-    println!("Copied result to clipboard");
-    Ok(())
+    let listed = titles
+        .iter()
+        .map(|&title| Listed {
+            title,
+            snippet: &merged.snippets[title],
+            source: (names.len() > 1).then(|| names[merged.source(title)].as_str()),
+        })
+        .collect();
+    let mut select = Select::new(message, listed);
+    if let Some(query) = query {
+        let texts = select.options.iter().map(|l| l.to_string()).collect();
+        match best_match(&titles, texts, query)? {
+            Some(index) => return Ok(titles[index]),
+            None => select = select.with_starting_filter_input(query),
+        }
+    }
+
+    let chosen = select.raw_prompt()?;
+    Ok(titles[chosen.index])
 }
 
-pub fn add_to_file(path: PathBuf) -> Result<()> {
-    let mut map: Snippets = get_snippets_from_file(&path).unwrap_or_default();
-    let title = Text::new("Title: ").prompt()?;
-    let snippet = Text::new("Snippet: ")
-        .prompt()?
-        .replace("\\n", "\n");
+/// Index of the snippet matching the query best, scored the same way as the list filters
+/// them. A title written exactly as the query wins, none is returned when there is a tie.
+fn best_match(titles: &[&String], texts: Vec<String>, query: &str) -> Result<Option<usize>> {
+    if let Some(index) = titles.iter().position(|t| t.eq_ignore_ascii_case(query)) {
+        return Ok(Some(index));
+    }
 
-    map.insert(title, snippet);
-    serde_json::to_writer(File::create(&path)?, &map)?;
-    Ok(())
+    let scorer = Select::<String>::DEFAULT_SCORER;
+    let scores = texts
+        .iter()
+        .enumerate()
+        .map(|(i, text)| scorer(query, text, text, i))
+        .collect::<Vec<_>>();
+    let Some(best) = scores.iter().flatten().max() else {
+        bail!("No snippet matches {query:?}")
+    };
+
+    let mut best_ones = (0..scores.len()).filter(|i| scores[*i] == Some(*best));
+    match (best_ones.next(), best_ones.next()) {
+        (Some(index), None) => Ok(Some(index)),
+        _ => Ok(None),
+    }
 }
 
-pub fn edit_file(path: PathBuf) -> Result<()> {
-    let mut map: Snippets = get_snippets_from_file(&path)?;
+/// The snippet with the exact title, or else the one snippet with it as an alias.
+pub fn find<'a>(snippets: &'a Snippets, title: &str) -> Result<&'a String> {
+    if let Some((title, _)) = snippets.get_key_value(title) {
+        return Ok(title);
+    }
 
-    let key = Select::new("Choose snippet to edit", map.keys().collect())
-        .prompt()?
-        .to_owned();
+    let aliased = snippets
+        .iter()
+        .filter(|(_, snippet)| snippet.aliases.iter().any(|a| a == title))
+        .map(|(title, _)| title)
+        .collect::<Vec<_>>();
+    match aliased.as_slice() {
+        [found] => Ok(found),
+        [] => bail!("There is no snippet titled {title:?}"),
+        found => bail!(
+            "{title:?} is an alias of several snippets: {}",
+            found
+                .iter()
+                .map(|t| format!("{t:?}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
 
-    let snippet = map.get(&key).unwrap();
+/// Lets the user pick the layer a snippet is saved to among the ones which can be
+/// changed, starting at the preferred layer or the last one.
+pub fn choose_layer(layers: &Layers, preferred: Option<usize>) -> Result<usize> {
+    let writable = layers.writable();
+    match writable.as_slice() {
+        [] => bail!("None of the snippet paths can be changed"),
+        [index] => return Ok(*index),
+        _ => {}
+    }
 
-    let title = Text::new("Title: ")
-        .with_initial_value(&key)
+    let names = names(layers);
+    let options = writable.iter().map(|&i| &names[i]).collect::<Vec<_>>();
+    let start = preferred
+        .and_then(|p| writable.iter().position(|&i| i == p))
+        .unwrap_or(writable.len() - 1);
+    let chosen = Select::new("Save to", options)
+        .with_starting_cursor(start)
+        .raw_prompt()?;
+    Ok(writable[chosen.index])
+}
+
+pub fn names(layers: &Layers) -> Vec<String> {
+    (0..layers.len()).map(|i| layers.name(i)).collect()
+}
+
+/// Prompts for the body and metadata of a snippet, starting from its current values.
+/// Leaving a field empty removes it.
+fn prompt_snippet(snippet: &Snippet) -> Result<Snippet> {
+    let body = Text::new("Snippet: ")
+        .with_initial_value(&to_prompt_text(&snippet.body))
+        .prompt()?;
+    let description = Text::new("Description: ")
+        .with_initial_value(snippet.description.as_deref().unwrap_or_default())
+        .prompt()?;
+    let tags = Text::new("Tags: ")
+        .with_initial_value(&snippet.tags.join(", "))
+        .with_help_message("Separated by commas")
+        .prompt()?;
+    let language = Text::new("Language: ")
+        .with_initial_value(snippet.language.as_deref().unwrap_or_default())
         .prompt()?;
+    let aliases = Text::new("Aliases: ")
+        .with_initial_value(&snippet.aliases.join(", "))
+        .with_help_message("Separated by commas")
+        .prompt()?;
+
+    Ok(Snippet {
+        body: from_prompt_text(&body),
+        description: optional(&description),
+        tags: list(&tags),
+        language: optional(&language),
+        aliases: list(&aliases),
+    })
+}
 
-    let snippet = Text::new("Snippet: ")
-        .with_initial_value(snippet)
-        .prompt()?
-        .replace("\\n", "\n");
+fn optional(input: &str) -> Option<String> {
+    let input = input.trim();
+    (!input.is_empty()).then(|| input.to_owned())
+}
 
-    map.remove(&key);
-    map.insert(title, snippet);
-    serde_json::to_writer(File::create(&path)?, &map)?;
+fn list(input: &str) -> Vec<String> {
+    input.split(',').filter_map(optional).collect()
+}
+
+/// Snippets are edited on a single line, so new lines are shown as `\n` while editing.
+fn to_prompt_text(snippet: &str) -> String {
+    snippet.replace('\n', "\\n")
+}
 
-    Ok(())
+fn from_prompt_text(input: &str) -> String {
+    input.replace("\\n", "\n")
 }
 
-fn get_snippets_from_file(path: &Path) -> Result<Snippets> {
-    let file = File::open(path).with_context(|| format!("{path:?} could not be found"))?;
-    let reader = BufReader::new(file);
-    serde_json::from_reader(reader).with_context(|| format!("{path:?} is not a valid snippet JSON format"))
+#[cfg(test)]
+mod test {
+    use super::{best_match, find, from_prompt_text, list, optional, to_prompt_text};
+    use crate::{snippet::Snippet, Snippets};
+
+    #[test]
+    fn prompt_text_escapes_new_lines() {
+        assert_eq!(to_prompt_text("one\ntwo"), "one\\ntwo");
+        assert_eq!(from_prompt_text("one\\ntwo"), "one\ntwo");
+    }
+
+    #[test]
+    fn placeholders_survive_round_trip() {
+        let snippet = "server {\n    listen {port:8080};\n    name {host:localhost};\n}";
+        assert_eq!(from_prompt_text(&to_prompt_text(snippet)), snippet);
+    }
+
+    #[test]
+    fn empty_metadata_is_left_out() {
+        assert_eq!(optional("  "), None);
+        assert_eq!(optional(" sql "), Some("sql".to_owned()));
+        assert_eq!(list("db, ,postgres "), vec!["db", "postgres"]);
+        assert!(list("").is_empty());
+    }
+
+    #[test]
+    fn picks_the_best_match() {
+        let titles = ["docker run", "docker build", "git log"].map(str::to_owned);
+        let titles = titles.iter().collect::<Vec<_>>();
+        let texts = titles.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let best = |query| best_match(&titles, texts.clone(), query);
+        assert_eq!(best("glog").unwrap(), Some(2));
+        assert_eq!(best("DOCKER BUILD").unwrap(), Some(1));
+        assert_eq!(best("docker").unwrap(), None);
+        assert!(best("kubectl").is_err());
+    }
+
+    #[test]
+    fn finds_by_title_or_alias() {
+        let mut snippets: Snippets = ["docker run", "docker build", "log"]
+            .into_iter()
+            .map(|title| (title.to_owned(), Snippet::new(title)))
+            .collect();
+        snippets["docker run"].aliases = vec!["dr".to_owned(), "d".to_owned()];
+        snippets["docker build"].aliases = vec!["db".to_owned(), "d".to_owned()];
+        snippets["log"].aliases = vec!["docker run".to_owned()];
+
+        assert_eq!(find(&snippets, "docker run").unwrap(), "docker run");
+        assert_eq!(find(&snippets, "db").unwrap(), "docker build");
+        assert_eq!(
+            find(&snippets, "d").unwrap_err().to_string(),
+            "\"d\" is an alias of several snippets: \"docker run\", \"docker build\""
+        );
+        assert!(find(&snippets, "missing").is_err());
+    }
 }
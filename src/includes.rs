@@ -0,0 +1,162 @@
+use crate::sections::delimiters::Delimiters;
+use crate::Snippets;
+use anyhow::{bail, Result};
+
+/// Inlines other snippets of the same store referenced as `{>title}`, recursively,
+/// so their placeholders become part of the including snippet.
+pub fn expand(title: &str, snippets: &Snippets, delimiters: &Delimiters) -> Result<String> {
+    let mut chain = vec![title.to_owned()];
+    match snippets.get(title) {
+        Some(snippet) => expand_body(&snippet.body, snippets, delimiters, &mut chain),
+        None => bail!("Couldn't find snippet for key {title}"),
+    }
+}
+
+fn expand_body(
+    body: &str,
+    snippets: &Snippets,
+    delimiters: &Delimiters,
+    chain: &mut Vec<String>,
+) -> Result<String> {
+    let (open, close) = (&delimiters.open[..], &delimiters.close[..]);
+    let chars = body.chars().collect::<Vec<char>>();
+    let mut expanded = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let rest = &chars[i..];
+        // Escapes are read the way the section parser reads them and left for it to
+        // unescape, apart from a doubled backslash before an include
+        let escape = delimiters.escape(rest);
+        let backslash = escape.is_some_and(|(text, _)| text == ['\\']);
+        let at = if backslash { 2 } else { 0 };
+        let Some(title) = include(&rest[at..], open, close) else {
+            let len = escape.map_or(1, |(_, len)| len);
+            expanded.extend(&rest[..len]);
+            i += len;
+            continue;
+        };
+
+        if chain.contains(&title) {
+            let cycle = chain.join(" -> ");
+            bail!("Snippet includes itself: {cycle} -> {title}");
+        }
+
+        let Some(included) = snippets.get(&title) else {
+            let parent = chain.last().cloned().unwrap_or_default();
+            bail!("Snippet {title:?} included by {parent:?} does not exist");
+        };
+
+        chain.push(title.clone());
+        let text = expand_body(&included.body, snippets, delimiters, chain)?;
+        chain.pop();
+        // The parser only reads a doubled backslash as one right before a placeholder
+        if backslash {
+            expanded.push('\\');
+            if text.chars().collect::<Vec<_>>().starts_with(open) {
+                expanded.push('\\');
+            }
+        }
+        expanded.push_str(&text);
+        i += at + open.len() + 1 + title.chars().count() + close.len();
+    }
+
+    Ok(expanded)
+}
+
+/// Reads the title of an include such as `{>title}` at the start of the characters.
+fn include(chars: &[char], open: &[char], close: &[char]) -> Option<String> {
+    let rest = chars.strip_prefix(open)?.strip_prefix(&['>'])?;
+    let end = (0..rest.len()).find(|i| rest[*i..].starts_with(close))?;
+    let title = rest[..end].iter().collect::<String>();
+    (!title.is_empty() && !title.contains('\n')).then_some(title)
+}
+
+#[cfg(test)]
+mod test {
+    use super::expand;
+    use crate::sections::{delimiters::Delimiters, section_manager::SectionManager};
+    use crate::{snippet::Snippet, Snippets};
+
+    #[test]
+    fn returns_body_without_includes() {
+        let snippets = snippets(&[("a", "echo {name}")]);
+        assert_eq!(
+            expand("a", &snippets, &Delimiters::default()).unwrap(),
+            "echo {name}"
+        );
+    }
+
+    #[test]
+    fn inlines_recursively() {
+        let snippets = snippets(&[
+            ("script", "{>header}\necho {msg}"),
+            ("header", "{>shebang}\nset -euo pipefail"),
+            ("shebang", "#!/usr/bin/env bash"),
+        ]);
+        assert_eq!(
+            expand("script", &snippets, &Delimiters::default()).unwrap(),
+            "#!/usr/bin/env bash\nset -euo pipefail\necho {msg}"
+        );
+    }
+
+    #[test]
+    fn inlines_same_snippet_twice() {
+        let snippets = snippets(&[("a", "{>b} {>b}"), ("b", "{x}")]);
+        assert_eq!(
+            expand("a", &snippets, &Delimiters::default()).unwrap(),
+            "{x} {x}"
+        );
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let snippets = snippets(&[("a", "{>b}"), ("b", "{>c}"), ("c", "{>a}")]);
+        let error = expand("a", &snippets, &Delimiters::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Snippet includes itself: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn reports_missing_includes() {
+        let snippets = snippets(&[("a", "{>missing}")]);
+        let error = expand("a", &snippets, &Delimiters::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Snippet \"missing\" included by \"a\" does not exist"
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_includes() {
+        let snippets = snippets(&[("a", "\\{>b} {>}"), ("b", "b")]);
+        assert_eq!(
+            expand("a", &snippets, &Delimiters::default()).unwrap(),
+            "\\{>b} {>}"
+        );
+    }
+
+    #[test]
+    fn inlines_after_escaped_backslashes() {
+        let snippets = snippets(&[("a", "\\\\{>b} \\\\{>c}"), ("b", "{x}"), ("c", "c")]);
+        let expanded = expand("a", &snippets, &Delimiters::default()).unwrap();
+        assert_eq!(expanded, "\\\\{x} \\c");
+        let manager = SectionManager::new(&expanded);
+        assert_eq!(manager.text(), "\\ \\c");
+    }
+
+    #[test]
+    fn uses_delimiters() {
+        let snippets = snippets(&[("a", "{>b} <<>b>>"), ("b", "b")]);
+        let delimiters = "<<>>".parse().unwrap();
+        assert_eq!(expand("a", &snippets, &delimiters).unwrap(), "{>b} b");
+    }
+
+    fn snippets(entries: &[(&str, &str)]) -> Snippets {
+        entries
+            .iter()
+            .map(|(title, body)| (title.to_string(), Snippet::new(body)))
+            .collect()
+    }
+}
@@ -0,0 +1,171 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Keys used while filling in a snippet.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Keys {
+    pub next: Key,
+    pub previous: Key,
+    pub add_row: Key,
+    pub remove_row: Key,
+    /// Stops editing, copying the snippet when every placeholder is valid.
+    pub quit: Key,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            next: Key::new(KeyCode::Enter, KeyModifiers::NONE),
+            previous: Key::new(KeyCode::Esc, KeyModifiers::NONE),
+            add_row: Key::new(KeyCode::Char('n'), KeyModifiers::CONTROL),
+            remove_row: Key::new(KeyCode::Char('d'), KeyModifiers::CONTROL),
+            quit: Key::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        }
+    }
+}
+
+/// A key along with its modifiers, written such as `enter`, `tab` or `ctrl-n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Key { code, modifiers }
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        // Shift is already part of the character typed
+        let modifiers = match event.code {
+            KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        self.code == event.code && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s.trim();
+        // A lone `-` is a key as well
+        while let Some((modifier, key)) = rest.split_once('-').filter(|(_, k)| !k.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("{modifier:?} is not a modifier of {s:?}")),
+            };
+            rest = key;
+        }
+
+        let code = match rest.to_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers -= KeyModifiers::SHIFT;
+                KeyCode::BackTab
+            }
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            key => match key.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => {
+                    let mut chars = rest.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+                        _ => return Err(format!("{s:?} is not a key")),
+                    }
+                }
+            },
+        };
+
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl-"),
+            (KeyModifiers::ALT, "Alt-"),
+            (KeyModifiers::SHIFT, "Shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}")?;
+            }
+        }
+
+        match self.code {
+            KeyCode::BackTab => write!(f, "Shift-Tab"),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Key;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn parses_keys() {
+        let key: Key = "Ctrl-N".parse().unwrap();
+        assert_eq!(key, Key::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        let key: Key = "shift-tab".parse().unwrap();
+        assert_eq!(key, Key::new(KeyCode::BackTab, KeyModifiers::NONE));
+        let key: Key = "alt--".parse().unwrap();
+        assert_eq!(key, Key::new(KeyCode::Char('-'), KeyModifiers::ALT));
+        assert_eq!(
+            "f2".parse(),
+            Ok(Key::new(KeyCode::F(2), KeyModifiers::NONE))
+        );
+        assert!("hyper-a".parse::<Key>().is_err());
+        assert!("enterr".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        for key in ["Enter", "Esc", "Ctrl-n", "Shift-Tab", "Alt-Space", "F5"] {
+            assert_eq!(key.parse::<Key>().unwrap().to_string(), key);
+        }
+    }
+
+    #[test]
+    fn matches_events() {
+        let key: Key = "ctrl-n".parse().unwrap();
+        assert!(key.matches(&KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL)));
+        assert!(!key.matches(&KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE)));
+        let key: Key = "?".parse().unwrap();
+        assert!(key.matches(&KeyEvent::new(KeyCode::Char('?'), KeyModifiers::SHIFT)));
+    }
+}
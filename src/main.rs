@@ -1,11 +1,22 @@
 mod args;
+mod commands;
+mod config;
 mod engine_modes;
+mod includes;
+mod keys;
+mod output;
 mod snippet_engine;
 mod sections;
+mod snippet;
+mod store;
+mod theme;
 
 use anyhow::Result;
-use std::{collections::HashMap, io::stdout};
-use args::Args;
+use indexmap::IndexMap;
+use snippet::Snippet;
+use std::io::stderr;
+use args::{Args, Command, ConfigCommand};
+use commands::*;
 use clap::Parser;
 use crossterm::{
     cursor, execute,
@@ -13,22 +24,42 @@ use crossterm::{
 };
 use engine_modes::*;
 
-type Snippets = HashMap<String, String>;
+/// Snippets by title, in the order they are written in the file.
+type Snippets = IndexMap<String, Snippet>;
 
 fn main() -> Result<()> {
-    let config = Args::parse();
-    execute!(stdout(), EnterAlternateScreen, cursor::MoveTo(0, 0))?;
+    let mut config = Args::parse();
+    config.check_command();
+    config.load_config()?;
 
-    let engine = if config.add {
-        add_to_file
-    } else if config.edit {
-        edit_file
-    } else {
-        start_editing_engine
-    };
+    match config.take_command() {
+        Command::Use(args) => {
+            let text = interactive(|| start_editing_engine(&config, args))?;
+            config.output().write(text)
+        }
+        Command::Add(args) => interactive(|| add_to_file(&config, args)),
+        Command::Edit(args) => interactive(|| edit_file(&config, args)),
+        Command::Rm(args) => remove(&config, args),
+        Command::Mv(args) => rename(&config, args),
+        Command::List(args) => list(&config, args),
+        Command::Show(args) => show(&config, args),
+        Command::Search(args) => search(&config, args),
+        Command::Import(args) => import(&config, args),
+        Command::Export(args) => export(&config, args),
+        Command::Render(args) => render(&config, args),
+        Command::Lint(args) => lint(&config, args),
+        Command::Config {
+            command: ConfigCommand::Show,
+        } => show_config(&config),
+    }
+}
 
-    let result = engine(config.path);
-    execute!(stdout(), LeaveAlternateScreen)?;
-    result?;
-    Ok(())
+/// Runs the commands prompting for snippets in the alternate screen, leaving the
+/// terminal as it was once done. Prompts are drawn on stderr so stdout is left for the
+/// snippet, such as with `$(snippset -o stdout)`.
+fn interactive<T>(engine: impl FnOnce() -> Result<T>) -> Result<T> {
+    execute!(stderr(), EnterAlternateScreen, cursor::MoveTo(0, 0))?;
+    let result = engine();
+    execute!(stderr(), LeaveAlternateScreen)?;
+    result
 }
@@ -0,0 +1,191 @@
+use crate::sections::command::shell;
+use anyhow::{bail, Context, Result};
+use arboard::Clipboard;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+/// Where the filled in snippet goes, written such as `stdout` or `file:notes.txt`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Output {
+    /// Copied, falling back to a clipboard tool and then to stdout when there is no
+    /// clipboard such as on a headless machine or over SSH.
+    #[default]
+    Clipboard,
+    Stdout,
+    /// Replaces the content of the file.
+    File(PathBuf),
+    /// Added to the end of the file on a line of its own.
+    Append(PathBuf),
+    /// Run as a shell command.
+    Exec,
+}
+
+/// Clipboard tools tried in order when the system clipboard can't be used directly.
+const CLIPBOARD_TOOLS: [&[&str]; 5] = [
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+    &["clip"],
+];
+
+impl Output {
+    /// Writes the snippet, messages about where it went are printed on stderr so only the
+    /// snippet itself ends up on stdout.
+    pub fn write(&self, text: String) -> Result<()> {
+        match self {
+            Output::Clipboard => match copy(&text) {
+                Ok(()) => eprintln!("Copied result to clipboard"),
+                Err(e) => {
+                    eprintln!("Could not copy the result, {e:#}");
+                    println!("{text}");
+                }
+            },
+            Output::Stdout => println!("{text}"),
+            Output::File(path) => {
+                fs::write(path, text).with_context(|| format!("{path:?} could not be written"))?;
+                eprintln!("Wrote result to {path:?}");
+            }
+            Output::Append(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("{path:?} could not be opened"))?;
+                writeln!(file, "{text}").with_context(|| format!("{path:?} could not be written"))?;
+                eprintln!("Appended result to {path:?}");
+            }
+            Output::Exec => {
+                let status = shell(&text).status().context("Could not run the result")?;
+                if !status.success() {
+                    bail!("The result failed with {status}")
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Copies the text to the system clipboard, or with the first clipboard tool that works.
+fn copy(text: &str) -> Result<()> {
+    let error = match Clipboard::new().and_then(|mut c| c.set_text(text)) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+
+    for tool in CLIPBOARD_TOOLS {
+        if pipe(tool, text).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(error).context("there is no clipboard")
+}
+
+fn pipe(tool: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(tool[0])
+        .args(&tool[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .context("No stdin")?
+        .write_all(text.as_bytes())?;
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => bail!("{} failed", tool[0]),
+    }
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = |path: &str| match path.is_empty() {
+            true => Err(format!("{s:?} needs a path after the colon")),
+            false => Ok(PathBuf::from(path)),
+        };
+
+        match s.split_once(':') {
+            Some(("file", rest)) => Ok(Output::File(path(rest)?)),
+            Some(("append", rest)) => Ok(Output::Append(path(rest)?)),
+            _ => match s {
+                "clipboard" => Ok(Output::Clipboard),
+                "stdout" => Ok(Output::Stdout),
+                "exec" => Ok(Output::Exec),
+                _ => Err(format!(
+                    "{s:?} is not an output, use clipboard, stdout, file:<path>, append:<path> or exec"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Clipboard => write!(f, "clipboard"),
+            Output::Stdout => write!(f, "stdout"),
+            Output::File(path) => write!(f, "file:{}", path.display()),
+            Output::Append(path) => write!(f, "append:{}", path.display()),
+            Output::Exec => write!(f, "exec"),
+        }
+    }
+}
+
+impl TryFrom<String> for Output {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Output> for String {
+    fn from(output: Output) -> Self {
+        output.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Output;
+    use std::{fs, path::PathBuf};
+    use tempfile::TempDir;
+
+    #[test]
+    fn parses_outputs() {
+        assert_eq!("stdout".parse(), Ok(Output::Stdout));
+        assert_eq!(
+            "file:C:\\notes.txt".parse(),
+            Ok(Output::File(PathBuf::from("C:\\notes.txt")))
+        );
+        assert!("file:".parse::<Output>().is_err());
+        assert!("printer".parse::<Output>().is_err());
+        for output in ["clipboard", "exec", "append:log/notes.md"] {
+            assert_eq!(output.parse::<Output>().unwrap().to_string(), output);
+        }
+    }
+
+    #[test]
+    fn writes_and_appends_to_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.txt");
+        Output::Append(path.clone()).write("one".to_owned()).unwrap();
+        Output::Append(path.clone()).write("two".to_owned()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        Output::File(path.clone()).write("three".to_owned()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "three");
+    }
+}
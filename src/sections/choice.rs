@@ -0,0 +1,104 @@
+/// A fixed list of values a placeholder can take, cycled through instead of typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice {
+    options: Vec<String>,
+    selected: usize,
+}
+
+impl Choice {
+    pub fn new(options: Vec<String>) -> Self {
+        Choice {
+            options,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.options.len();
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = (self.selected + self.options.len() - 1) % self.options.len();
+    }
+
+    /// Jumps to the next option starting with the given character, wrapping around.
+    pub fn select_starting_with(&mut self, c: char) {
+        let c = c.to_lowercase().collect::<String>();
+        let len = self.options.len();
+        if let Some(i) = (1..=len)
+            .map(|offset| (self.selected + offset) % len)
+            .find(|i| self.options[*i].to_lowercase().starts_with(&c))
+        {
+            self.selected = i;
+        }
+    }
+
+    /// Selects the option with the given value, returning whether there is one.
+    pub fn select(&mut self, value: &str) -> bool {
+        match self.options.iter().position(|o| o == value) {
+            Some(i) => {
+                self.selected = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.options[self.selected]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Choice;
+
+    #[test]
+    fn selects_first_option() {
+        let choice = create_choice(&["GET", "POST"]);
+        assert_eq!(choice.value(), "GET");
+    }
+
+    #[test]
+    fn cycles_forward() {
+        let mut choice = create_choice(&["GET", "POST", "PUT"]);
+        choice.next();
+        assert_eq!(choice.value(), "POST");
+        choice.next();
+        choice.next();
+        assert_eq!(choice.value(), "GET");
+    }
+
+    #[test]
+    fn cycles_backward() {
+        let mut choice = create_choice(&["GET", "POST", "PUT"]);
+        choice.previous();
+        assert_eq!(choice.value(), "PUT");
+        choice.previous();
+        assert_eq!(choice.value(), "POST");
+    }
+
+    #[test]
+    fn selects_by_first_character() {
+        let mut choice = create_choice(&["GET", "POST", "PUT", "DELETE"]);
+        choice.select_starting_with('p');
+        assert_eq!(choice.value(), "POST");
+        choice.select_starting_with('p');
+        assert_eq!(choice.value(), "PUT");
+        choice.select_starting_with('x');
+        assert_eq!(choice.value(), "PUT");
+    }
+
+    #[test]
+    fn selects_by_value() {
+        let mut choice = create_choice(&["GET", "POST"]);
+        assert!(choice.select("POST"));
+        assert_eq!(choice.value(), "POST");
+        assert!(!choice.select("get"));
+        assert_eq!(choice.value(), "POST");
+    }
+
+    fn create_choice(options: &[&str]) -> Choice {
+        Choice::new(options.iter().map(|o| o.to_string()).collect())
+    }
+}
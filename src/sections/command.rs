@@ -0,0 +1,117 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Runs a shell command and returns its trimmed standard output, failing with its
+/// standard error when it exits unsuccessfully or does not finish within the timeout.
+pub fn run(command: &str, timeout: Duration) -> Result<String> {
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run `{command}`"))?;
+
+    // Read in the background so a command with a lot of output does not block on a full pipe
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            bail!("`{command}` timed out after {:?}", timeout);
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    // A process started in the background can keep the pipes open after the command
+    // exits, its readers are left behind once the timeout runs out
+    let collect = |output: Receiver<String>| match output
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    {
+        Err(RecvTimeoutError::Timeout) => None,
+        received => Some(received.unwrap_or_default()),
+    };
+    let (Some(stdout), Some(stderr)) = (collect(stdout), collect(stderr)) else {
+        bail!("`{command}` timed out after {timeout:?}, something it started kept its output open");
+    };
+    if !status.success() {
+        let stderr = stderr.trim();
+        match stderr.is_empty() {
+            true => bail!("`{command}` failed with {status}"),
+            false => bail!("`{command}` failed: {stderr}"),
+        }
+    }
+
+    Ok(stdout.trim().to_owned())
+}
+
+#[cfg(windows)]
+pub fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(not(windows))]
+pub fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut output);
+        }
+        let _ = sender.send(output);
+    });
+    receiver
+}
+
+#[cfg(all(test, not(windows)))]
+mod test {
+    use super::run;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn returns_trimmed_stdout() {
+        let output = run("echo '  hello  '", Duration::from_secs(5)).unwrap();
+        assert_eq!(output, "hello");
+    }
+
+    #[test]
+    fn fails_with_stderr() {
+        let error = run("echo oops >&2; exit 3", Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.to_string(), "`echo oops >&2; exit 3` failed: oops");
+    }
+
+    #[test]
+    fn fails_on_timeout() {
+        let error = run("sleep 5", Duration::from_millis(50)).unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+    }
+
+    #[test]
+    fn leaves_output_of_background_processes_behind() {
+        let start = Instant::now();
+        let error = run("sleep 5 & echo started", Duration::from_millis(200)).unwrap_err();
+        assert!(error.to_string().contains("kept its output open"));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
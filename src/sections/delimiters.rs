@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Character sequences marking the start and end of a placeholder, `{` and `}` by default.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Delimiters {
+    pub open: Vec<char>,
+    pub close: Vec<char>,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Delimiters {
+            open: vec!['{'],
+            close: vec!['}'],
+        }
+    }
+}
+
+impl Delimiters {
+    /// Returns the delimiter escaped by a leading backslash, if any.
+    pub fn escaped(&self, chars: &[char]) -> Option<&[char]> {
+        let rest = chars.strip_prefix(&['\\'])?;
+        [&self.open[..], &self.close[..]]
+            .into_iter()
+            .find(|delimiter| rest.starts_with(delimiter))
+    }
+
+    /// Reads an escape at the start of the characters, returning the text it stands for
+    /// along with its length. A backslash before a delimiter such as `\{` stands for the
+    /// delimiter, while a doubled backslash right before an opening delimiter such as
+    /// `\\{}` stands for a backslash followed by a placeholder.
+    pub fn escape(&self, chars: &[char]) -> Option<(&[char], usize)> {
+        if let Some(escaped) = self.escaped(chars) {
+            return Some((escaped, escaped.len() + 1));
+        }
+
+        let rest = chars.strip_prefix(&['\\', '\\'])?;
+        rest.starts_with(&self.open).then_some((&['\\'], 2))
+    }
+}
+
+impl FromStr for Delimiters {
+    type Err = String;
+
+    /// Accepts the opening and closing delimiters separated by a space such as `${ }`,
+    /// or an even length string split in half such as `<<>>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (open, close) = match s.split_once(char::is_whitespace) {
+            Some((open, close)) => (open.trim(), close.trim()),
+            None if s.chars().count().is_multiple_of(2) => {
+                let middle = s.char_indices().nth(s.chars().count() / 2).map(|(i, _)| i);
+                s.split_at(middle.unwrap_or_default())
+            }
+            None => return Err(format!("Could not split {s:?} into opening and closing delimiters, separate them with a space")),
+        };
+
+        if open.is_empty() || close.is_empty() {
+            return Err("Opening and closing delimiters can not be empty".to_owned());
+        }
+
+        if open.contains('\\') || close.contains('\\') {
+            return Err("Delimiters can not contain '\\' since it is used for escaping".to_owned());
+        }
+
+        Ok(Delimiters {
+            open: open.chars().collect(),
+            close: close.chars().collect(),
+        })
+    }
+}
+
+impl fmt::Display for Delimiters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let open = self.open.iter().collect::<String>();
+        let close = self.close.iter().collect::<String>();
+        write!(f, "{open} {close}")
+    }
+}
+
+impl TryFrom<String> for Delimiters {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Delimiters> for String {
+    fn from(delimiters: Delimiters) -> Self {
+        delimiters.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Delimiters;
+
+    #[test]
+    fn defaults_to_braces() {
+        let delimiters = Delimiters::default();
+        assert_eq!(delimiters.open, vec!['{']);
+        assert_eq!(delimiters.close, vec!['}']);
+    }
+
+    #[test]
+    fn parses_space_separated() {
+        let delimiters: Delimiters = "${ }".parse().unwrap();
+        assert_eq!(delimiters.open, vec!['$', '{']);
+        assert_eq!(delimiters.close, vec!['}']);
+    }
+
+    #[test]
+    fn parses_even_length() {
+        let delimiters: Delimiters = "<<>>".parse().unwrap();
+        assert_eq!(delimiters.open, vec!['<', '<']);
+        assert_eq!(delimiters.close, vec!['>', '>']);
+    }
+
+    #[test]
+    fn rejects_ambiguous() {
+        assert!("${}".parse::<Delimiters>().is_err());
+        assert!("".parse::<Delimiters>().is_err());
+        assert!("\\{ }".parse::<Delimiters>().is_err());
+    }
+
+    #[test]
+    fn reads_escapes() {
+        let delimiters = Delimiters::default();
+        let escape = |text: &str| {
+            let chars = text.chars().collect::<Vec<_>>();
+            delimiters
+                .escape(&chars)
+                .map(|(text, len)| (text.iter().collect::<String>(), len))
+        };
+        assert_eq!(escape("\\{x}"), Some(("{".to_owned(), 2)));
+        assert_eq!(escape("\\}"), Some(("}".to_owned(), 2)));
+        assert_eq!(escape("\\\\{x}"), Some(("\\".to_owned(), 2)));
+        assert_eq!(escape("\\\\x"), None);
+        assert_eq!(escape("\\d"), None);
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        let delimiters: Delimiters = "<<>>".parse().unwrap();
+        assert_eq!(delimiters.to_string(), "<< >>");
+        assert_eq!(delimiters.to_string().parse(), Ok(delimiters));
+    }
+}
@@ -1,7 +1,8 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EditableText {
     cursor: usize,
     chars: Vec<char>,
+    selected: bool,
 }
 
 impl EditableText {
@@ -9,37 +10,68 @@ impl EditableText {
         EditableText {
             cursor: 0,
             chars: Vec::new(),
+            selected: false,
+        }
+    }
+
+    /// Pre-fills the text with a default value which stays selected until the user
+    /// interacts with it, typing replaces the default while moving the cursor keeps it.
+    pub fn with_default(default: &str) -> Self {
+        let chars: Vec<char> = default.chars().collect();
+        EditableText {
+            cursor: chars.len(),
+            selected: !chars.is_empty(),
+            chars,
         }
     }
 
     pub fn insert(&mut self, c: char) {
+        if self.selected {
+            self.clear();
+        }
+
         self.cursor += 1;
         self.chars.insert(self.cursor - 1, c);
     }
 
     pub fn delete(&mut self) {
-        if self.cursor > 0 {
+        if self.selected {
+            self.clear();
+        } else if self.cursor > 0 {
             self.chars.remove(self.cursor - 1);
             self.cursor -= 1;
         }
     }
 
     pub fn move_left(&mut self) {
+        self.selected = false;
         if self.cursor > 0 {
             self.cursor -= 1;
         }
     }
 
     pub fn move_right(&mut self) {
+        self.selected = false;
         if self.cursor < self.chars.len() {
             self.cursor += 1;
         }
     }
 
     pub fn cursor_to_right_edge(&mut self) {
+        self.selected = false;
         self.cursor = self.chars.len();
     }
 
+    fn clear(&mut self) {
+        self.chars.clear();
+        self.cursor = 0;
+        self.selected = false;
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.selected
+    }
+
     pub fn chars(&self) -> Vec<char> {
         self.chars.clone()
     }
@@ -175,6 +207,53 @@ mod test {
         assert_eq!(editable.insertion_position(), 0);
     }
 
+    #[test]
+    fn default_is_selected() {
+        let editable = EditableText::with_default("8080");
+        assert_eq!("8080".chars().collect::<Vec<char>>(), editable.chars());
+        assert_eq!(editable.insertion_position(), 4);
+        assert!(editable.is_selected());
+    }
+
+    #[test]
+    fn empty_default_is_not_selected() {
+        let editable = EditableText::with_default("");
+        assert!(!editable.is_selected());
+    }
+
+    #[test]
+    fn typing_replaces_default() {
+        let mut editable = EditableText::with_default("8080");
+        editable.insert('9');
+        editable.insert('0');
+        assert_eq!("90".chars().collect::<Vec<char>>(), editable.chars());
+        assert!(!editable.is_selected());
+    }
+
+    #[test]
+    fn deleting_clears_default() {
+        let mut editable = EditableText::with_default("8080");
+        editable.delete();
+        assert!(editable.chars().is_empty());
+        assert_eq!(editable.insertion_position(), 0);
+    }
+
+    #[test]
+    fn moving_keeps_default() {
+        let mut editable = EditableText::with_default("8080");
+        editable.move_left();
+        editable.insert('1');
+        assert_eq!("80810".chars().collect::<Vec<char>>(), editable.chars());
+    }
+
+    #[test]
+    fn accepting_keeps_default() {
+        let mut editable = EditableText::with_default("8080");
+        editable.cursor_to_right_edge();
+        assert!(!editable.is_selected());
+        assert_eq!("8080".chars().collect::<Vec<char>>(), editable.chars());
+    }
+
     fn create_editable(suffix: &str) -> EditableText {
         let mut editable = EditableText::new();
         suffix.chars().for_each(|c| editable.insert(c));
@@ -1,3 +1,12 @@
+pub mod choice;
+pub mod command;
+pub mod delimiters;
 pub mod editable_text;
+pub mod parse_options;
+pub mod placeholder;
 pub mod section;
 pub mod section_manager;
+pub mod textmate;
+pub mod transform;
+pub mod validation;
+pub mod variables;
@@ -0,0 +1,34 @@
+use super::delimiters::Delimiters;
+use std::time::Duration;
+
+/// Syntax the placeholders of a snippet are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// Placeholders such as `{name:default}`.
+    #[default]
+    Snippset,
+    /// Placeholders such as `${1:default}` used by VS Code and TextMate snippets.
+    TextMate,
+}
+
+/// Settings deciding how a snippet is turned into sections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub delimiters: Delimiters,
+    /// Commands such as `{$(whoami)}` only run when explicitly allowed, so opening an
+    /// untrusted snippet file never executes anything.
+    pub allow_commands: bool,
+    pub command_timeout: Duration,
+    pub syntax: Syntax,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            delimiters: Delimiters::default(),
+            allow_commands: false,
+            command_timeout: Duration::from_secs(5),
+            syntax: Syntax::Snippset,
+        }
+    }
+}
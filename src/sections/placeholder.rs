@@ -0,0 +1,312 @@
+use super::{transform::Transform, validation::Validation, variables::Variable};
+
+/// Content written between the braces of a placeholder, such as `{name:default}`,
+/// `{name|first,second}` for a choice, `{name/snake}` for a transformed mirror or
+/// `{count!int}` for a typed value which can also have a default as `{count!int:1}`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Placeholder {
+    pub name: Option<String>,
+    pub default: String,
+    pub choices: Vec<String>,
+    /// Variable resolved into the default value when the snippet is opened, such as `{date:$DATE}`.
+    pub variable: Option<Variable>,
+    /// Command whose output becomes the default value when the snippet is opened, such as `{$(whoami)}`.
+    pub command: Option<String>,
+    pub transform: Option<Transform>,
+    pub validation: Option<Validation>,
+}
+
+impl Placeholder {
+    /// Parses the content between braces, anything that is not a valid placeholder
+    /// returns `None` so it can be kept as plain text.
+    pub fn parse(content: &[char]) -> Option<Self> {
+        if let Some(command) = Placeholder::command(content) {
+            return Some(Placeholder {
+                command: Some(command),
+                ..Placeholder::default()
+            });
+        }
+
+        let separator = content
+            .iter()
+            .position(|c| matches!(c, ':' | '|' | '/' | '!'));
+        let (name, rest) = match separator {
+            Some(i) => (&content[..i], &content[i + 1..]),
+            None => (content, &[][..]),
+        };
+
+        if !name
+            .iter()
+            .all(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+        {
+            return None;
+        }
+
+        let name = (!name.is_empty()).then(|| name.iter().collect());
+        match separator.map(|i| content[i]) {
+            Some('|') => {
+                let choices: Vec<String> = rest
+                    .split(|c| *c == ',')
+                    .map(|option| option.iter().collect())
+                    .filter(|option: &String| !option.is_empty())
+                    .collect();
+
+                if choices.is_empty() {
+                    return None;
+                }
+
+                Some(Placeholder {
+                    name,
+                    default: choices[0].clone(),
+                    choices,
+                    ..Placeholder::default()
+                })
+            }
+            // Transforms only make sense on a mirror of a named placeholder
+            Some('/') => Some(Placeholder {
+                transform: Some(Transform::parse(rest)?),
+                name: Some(name?),
+                ..Placeholder::default()
+            }),
+            Some('!') => {
+                let (validation, rest) = Placeholder::validation(rest)?;
+                Some(Placeholder {
+                    validation: Some(validation),
+                    ..Placeholder::value(name, rest)
+                })
+            }
+            _ => Some(Placeholder::value(name, rest)),
+        }
+    }
+
+    /// Whether the content so far starts a regex type such as `email!regex=`, whose
+    /// pattern may hold balanced delimiters such as the quantifier in `\d{5}`.
+    pub fn starts_regex(content: &[char]) -> bool {
+        let regex = ['!', 'r', 'e', 'g', 'e', 'x', '='];
+        content
+            .iter()
+            .position(|c| matches!(c, ':' | '|' | '/' | '!'))
+            .is_some_and(|i| content[i..].starts_with(&regex))
+    }
+
+    fn value(name: Option<String>, content: &[char]) -> Self {
+        let variable = Placeholder::variable(content);
+        let command = Placeholder::command(content);
+        let default = match variable.is_some() || command.is_some() {
+            true => String::new(),
+            false => content.iter().collect(),
+        };
+
+        Placeholder {
+            name,
+            default,
+            variable,
+            command,
+            ..Placeholder::default()
+        }
+    }
+
+    /// Splits the type off its default, a regex type always takes the whole rest.
+    fn validation(content: &[char]) -> Option<(Validation, &[char])> {
+        let text = content.iter().collect::<String>();
+        if text.starts_with("regex=") {
+            return Some((Validation::parse(&text)?, &[]));
+        }
+
+        let end = content
+            .iter()
+            .position(|c| *c == ':')
+            .unwrap_or(content.len());
+        let kind = content[..end].iter().collect::<String>();
+        Some((
+            Validation::parse(&kind)?,
+            content.get(end + 1..).unwrap_or_default(),
+        ))
+    }
+
+    fn variable(content: &[char]) -> Option<Variable> {
+        let name = content.strip_prefix(&['$'])?.iter().collect::<String>();
+        Variable::parse(&name)
+    }
+
+    fn command(content: &[char]) -> Option<String> {
+        let command = content.strip_prefix(&['$', '('])?.strip_suffix(&[')'])?;
+        let command = command.iter().collect::<String>();
+        (!command.trim().is_empty()).then_some(command)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Placeholder;
+    use crate::sections::{transform::Transform, validation::Validation, variables::Variable};
+
+    #[test]
+    fn parses_anonymous() {
+        let placeholder = parse("").unwrap();
+        assert_eq!(placeholder.name, None);
+        assert_eq!(placeholder.default, "");
+    }
+
+    #[test]
+    fn parses_name() {
+        let placeholder = parse("port").unwrap();
+        assert_eq!(placeholder.name, Some("port".to_owned()));
+        assert_eq!(placeholder.default, "");
+    }
+
+    #[test]
+    fn parses_default() {
+        let placeholder = parse("port:8080").unwrap();
+        assert_eq!(placeholder.name, Some("port".to_owned()));
+        assert_eq!(placeholder.default, "8080");
+    }
+
+    #[test]
+    fn parses_anonymous_default() {
+        let placeholder = parse(":http://localhost").unwrap();
+        assert_eq!(placeholder.name, None);
+        assert_eq!(placeholder.default, "http://localhost");
+    }
+
+    #[test]
+    fn parses_choices() {
+        let placeholder = parse("method|GET,POST,PUT").unwrap();
+        assert_eq!(placeholder.name, Some("method".to_owned()));
+        assert_eq!(placeholder.default, "GET");
+        assert_eq!(placeholder.choices, vec!["GET", "POST", "PUT"]);
+    }
+
+    #[test]
+    fn default_may_contain_choice_separator() {
+        let placeholder = parse("cmd:a|b").unwrap();
+        assert_eq!(placeholder.default, "a|b");
+        assert!(placeholder.choices.is_empty());
+    }
+
+    #[test]
+    fn rejects_empty_choices() {
+        assert_eq!(parse("method|"), None);
+        assert_eq!(parse("method|,,"), None);
+    }
+
+    #[test]
+    fn parses_variable_default() {
+        let placeholder = parse("date:$DATE").unwrap();
+        assert_eq!(placeholder.name, Some("date".to_owned()));
+        assert_eq!(placeholder.variable, Some(Variable::Date));
+        assert_eq!(placeholder.default, "");
+    }
+
+    #[test]
+    fn unknown_variable_is_plain_default() {
+        let placeholder = parse("price:$5").unwrap();
+        assert_eq!(placeholder.variable, None);
+        assert_eq!(placeholder.default, "$5");
+    }
+
+    #[test]
+    fn parses_command() {
+        let placeholder = parse("$(git rev-parse --abbrev-ref HEAD)").unwrap();
+        assert_eq!(placeholder.name, None);
+        assert_eq!(
+            placeholder.command,
+            Some("git rev-parse --abbrev-ref HEAD".to_owned())
+        );
+    }
+
+    #[test]
+    fn parses_command_default() {
+        let placeholder = parse("user:$(whoami)").unwrap();
+        assert_eq!(placeholder.name, Some("user".to_owned()));
+        assert_eq!(placeholder.command, Some("whoami".to_owned()));
+        assert_eq!(placeholder.default, "");
+    }
+
+    #[test]
+    fn rejects_empty_command() {
+        assert_eq!(parse("$( )"), None);
+    }
+
+    #[test]
+    fn parses_transform() {
+        let placeholder = parse("name/snake").unwrap();
+        assert_eq!(placeholder.name, Some("name".to_owned()));
+        assert_eq!(placeholder.transform, Some(Transform::Snake));
+    }
+
+    #[test]
+    fn parses_regex_transform() {
+        let placeholder = parse("path/\\//::/g").unwrap();
+        assert_eq!(placeholder.transform.unwrap().apply("a/b"), "a::b");
+    }
+
+    #[test]
+    fn rejects_invalid_transforms() {
+        assert_eq!(parse("/snake"), None);
+        assert_eq!(parse("name/unknown"), None);
+    }
+
+    #[test]
+    fn parses_type() {
+        let placeholder = parse("count!int").unwrap();
+        assert_eq!(placeholder.name, Some("count".to_owned()));
+        assert_eq!(placeholder.validation, Some(Validation::Int));
+        assert_eq!(placeholder.default, "");
+    }
+
+    #[test]
+    fn parses_type_with_default() {
+        let placeholder = parse("when!date:$DATE").unwrap();
+        assert_eq!(placeholder.validation, Some(Validation::Date));
+        assert_eq!(placeholder.variable, Some(Variable::Date));
+
+        let placeholder = parse("port!int:8080").unwrap();
+        assert_eq!(placeholder.validation, Some(Validation::Int));
+        assert_eq!(placeholder.default, "8080");
+    }
+
+    #[test]
+    fn type_keywords_are_plain_defaults() {
+        let placeholder = parse("mode:file").unwrap();
+        assert_eq!(placeholder.validation, None);
+        assert_eq!(placeholder.default, "file");
+    }
+
+    #[test]
+    fn parses_regex_type() {
+        let placeholder = parse("email!regex=^.+@.+:.+$").unwrap();
+        assert_eq!(
+            placeholder.validation,
+            Validation::parse("regex=^.+@.+:.+$")
+        );
+        assert_eq!(placeholder.default, "");
+    }
+
+    #[test]
+    fn rejects_invalid_types() {
+        assert_eq!(parse("x!regex=("), None);
+        assert_eq!(parse("x!number"), None);
+        assert_eq!(parse("!important"), None);
+    }
+
+    #[test]
+    fn finds_start_of_regex() {
+        let starts =
+            |content: &str| Placeholder::starts_regex(&content.chars().collect::<Vec<_>>());
+        assert!(starts("zip!regex=^\\d"));
+        assert!(starts("!regex="));
+        assert!(!starts("zip:regex=^\\d"));
+        assert!(!starts("zip!int"));
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert_eq!(parse(" \"key\": 1 "), None);
+        assert_eq!(parse("two words"), None);
+    }
+
+    fn parse(content: &str) -> Option<Placeholder> {
+        Placeholder::parse(&content.chars().collect::<Vec<char>>())
+    }
+}
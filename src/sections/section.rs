@@ -1,8 +1,31 @@
-use super::editable_text::EditableText;
-#[derive(Debug, PartialEq)]
+use super::{
+    choice::Choice, editable_text::EditableText, transform::Transform, validation::Validation,
+};
+#[derive(Clone, Debug, PartialEq)]
 pub struct Section {
     pub prefix: Vec<char>,
     pub suffix: Option<EditableText>,
+    pub name: Option<String>,
+    pub choice: Option<Choice>,
+    /// Problem shown to the user while this section is active, such as a failed command.
+    pub error: Option<String>,
+    pub transform: Option<Transform>,
+    pub validation: Option<Validation>,
+    /// Optional group such as `{[ -H "Auth: {token}" ]}` this section belongs to.
+    pub group: Option<usize>,
+    /// Row of a repeat block such as `{*field: {name},*}` this section belongs to.
+    pub row: Option<Row>,
+}
+
+/// A row of a repeat block, names of placeholders are scoped to their row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    /// Name of the block such as `field` in `{*field: {name},*}`.
+    pub name: String,
+    /// Text put between the rows, such as `, ` in `{*value: '{v}'*, }`.
+    pub separator: String,
+    pub repeat: usize,
+    pub index: usize,
 }
 
 impl Section {
@@ -10,33 +33,144 @@ impl Section {
         Section {
             prefix,
             suffix: Some(EditableText::new()),
+            name: None,
+            choice: None,
+            error: None,
+            transform: None,
+            validation: None,
+            group: None,
+            row: None,
         }
     }
 
+    pub fn named(prefix: Vec<char>, name: &str) -> Self {
+        Section {
+            prefix,
+            suffix: Some(EditableText::new()),
+            name: Some(name.to_owned()),
+            choice: None,
+            error: None,
+            transform: None,
+            validation: None,
+            group: None,
+            row: None,
+        }
+    }
+
+    pub fn with_default(mut self, default: &str) -> Self {
+        self.suffix = Some(EditableText::with_default(default));
+        self
+    }
+
+    pub fn with_choices(mut self, options: Vec<String>) -> Self {
+        let choice = Choice::new(options);
+        self.suffix = Some(EditableText::with_default(choice.value()));
+        self.choice = Some(choice);
+        self
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Option<Transform>) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_validation(mut self, validation: Option<Validation>) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn in_group(mut self, group: usize) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    pub fn in_row(mut self, row: Row) -> Self {
+        self.row = Some(row);
+        self
+    }
+
+    /// Whether both sections are occurrences of the same named placeholder, inside a repeat
+    /// block names only match within the same row.
+    pub fn mirrors(&self, other: &Section) -> bool {
+        self.name.is_some() && self.name == other.name && self.row == other.row
+    }
+
+    /// Applies a change to the choice of this section and shows the newly selected value.
+    pub fn choose(&mut self, update: impl FnOnce(&mut Choice)) {
+        if let Some(choice) = self.choice.as_mut() {
+            update(choice);
+            self.suffix = Some(EditableText::with_default(choice.value()));
+        }
+    }
+
+    /// The number of a tab-stop such as `${1}`, placeholders named with digits only.
+    pub fn tab_stop(&self) -> Option<usize> {
+        self.name.as_ref()?.parse().ok()
+    }
+
     pub fn tail(prefix: Vec<char>) -> Self {
         Section {
             prefix,
             suffix: None,
+            name: None,
+            choice: None,
+            error: None,
+            transform: None,
+            validation: None,
+            group: None,
+            row: None,
         }
     }
 
-    pub fn text(&self) -> String {
-        let prefix = self.prefix.iter().collect::<String>();
-        let suffix = self
+    /// Message for a value that does not match the placeholder's type.
+    pub fn validation_error(&self) -> Option<String> {
+        let validation = self.validation.as_ref()?;
+        let value = self
+            .suffix
+            .as_ref()?
+            .chars()
+            .into_iter()
+            .collect::<String>();
+        validation.validate(&value).err()
+    }
+
+    /// Problem to show the user while this section is active.
+    pub fn problem(&self) -> Option<String> {
+        self.error.clone().or_else(|| self.validation_error())
+    }
+
+    /// The value of the editable part with its transform applied.
+    pub fn value(&self) -> String {
+        let value = self
             .suffix
             .as_ref()
             .map(|e| e.chars().into_iter().collect::<String>())
             .unwrap_or_default();
 
-        format!("{prefix}{suffix}")
+        match &self.transform {
+            Some(transform) => transform.apply(&value),
+            None => value,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let prefix = self.prefix.iter().collect::<String>();
+        format!("{prefix}{}", self.value())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::sections::editable_text::EditableText;
+    use crate::sections::{
+        editable_text::EditableText, transform::Transform, validation::Validation,
+    };
 
-    use super::Section;
+    use super::{Row, Section};
 
     #[test]
     fn creates_correct_body() {
@@ -45,6 +179,87 @@ mod test {
         assert!(section.suffix.is_some())
     }
 
+    #[test]
+    fn creates_named_body() {
+        let section = Section::named("Hello ".chars().collect(), "name");
+        assert_eq!(section.prefix, "Hello ".chars().collect::<Vec<char>>());
+        assert_eq!(section.name, Some("name".to_owned()));
+        assert!(section.suffix.is_some())
+    }
+
+    #[test]
+    fn creates_body_with_default() {
+        let section = Section::named("port ".chars().collect(), "port").with_default("8080");
+        assert_eq!(section.suffix, Some(EditableText::with_default("8080")));
+        assert_eq!("port 8080".to_owned(), section.text());
+    }
+
+    #[test]
+    fn creates_body_with_choices() {
+        let section = Section::named("".chars().collect(), "level")
+            .with_choices(vec!["info".to_owned(), "warn".to_owned()]);
+        assert!(section.choice.is_some());
+        assert_eq!("info".to_owned(), section.text());
+    }
+
+    #[test]
+    fn choosing_updates_text() {
+        let mut section = Section::body("level=".chars().collect())
+            .with_choices(vec!["info".to_owned(), "warn".to_owned()]);
+        section.choose(|c| c.next());
+        assert_eq!("level=warn".to_owned(), section.text());
+    }
+
+    #[test]
+    fn reads_tab_stop() {
+        assert_eq!(Section::named(vec![], "2").tab_stop(), Some(2));
+        assert_eq!(Section::named(vec![], "name").tab_stop(), None);
+        assert_eq!(Section::body(vec![]).tab_stop(), None);
+    }
+
+    #[test]
+    fn mirrors_within_row() {
+        let row = |index| Row {
+            name: "field".to_owned(),
+            separator: String::new(),
+            repeat: 0,
+            index,
+        };
+        let section = Section::named(vec![], "name").in_row(row(0));
+        assert!(section.mirrors(&Section::named(vec![], "name").in_row(row(0))));
+        assert!(!section.mirrors(&Section::named(vec![], "name").in_row(row(1))));
+        assert!(!section.mirrors(&Section::named(vec![], "name")));
+        assert!(!Section::body(vec![]).mirrors(&Section::body(vec![])));
+    }
+
+    #[test]
+    fn applies_transform() {
+        let mut section =
+            Section::named("mod ".chars().collect(), "name").with_transform(Some(Transform::Snake));
+        section.suffix = Some(EditableText::with_default("MyType"));
+        assert_eq!("mod my_type".to_owned(), section.text());
+    }
+
+    #[test]
+    fn validates_value() {
+        let mut section = Section::named(vec![], "count").with_validation(Some(Validation::Int));
+        assert_eq!(
+            section.validation_error(),
+            Some("Expected a whole number".to_owned())
+        );
+        section.suffix = Some(EditableText::with_default("12"));
+        assert_eq!(section.validation_error(), None);
+        assert_eq!(section.problem(), None);
+    }
+
+    #[test]
+    fn errors_come_before_validation() {
+        let section = Section::body(vec![])
+            .with_validation(Some(Validation::Int))
+            .with_error(Some("failed".to_owned()));
+        assert_eq!(section.problem(), Some("failed".to_owned()));
+    }
+
     #[test]
     fn creates_tail() {
         let section = Section::tail("Hello world".chars().collect());
@@ -1,38 +1,130 @@
-use super::{editable_text::EditableText, section::Section};
+use super::{
+    command,
+    delimiters::Delimiters,
+    editable_text::EditableText,
+    parse_options::{ParseOptions, Syntax},
+    placeholder::Placeholder,
+    section::{Row, Section},
+    textmate,
+    variables::Variable,
+};
 use anyhow::{bail, Result};
 
 pub struct SectionManager {
     pub sections: Vec<Section>,
     pub active_index: usize,
+    tab_order: Vec<usize>,
+    position: usize,
+    /// Sections of a new row for each repeat block, as they were parsed.
+    templates: Vec<Vec<Section>>,
 }
 
 impl SectionManager {
     pub fn new(snippet: &str) -> Self {
-        SectionManager {
-            sections: SectionManager::parse_content(snippet),
+        SectionManager::with_options(snippet, &ParseOptions::default())
+    }
+
+    pub fn with_options(snippet: &str, options: &ParseOptions) -> Self {
+        let mut manager = SectionManager {
+            sections: match options.syntax {
+                Syntax::Snippset => SectionManager::parse_content(snippet, options),
+                Syntax::TextMate => textmate::parse(snippet),
+            },
             active_index: 0,
-        }
+            tab_order: Vec::new(),
+            position: 0,
+            templates: Vec::new(),
+        };
+
+        manager.share_defaults();
+        manager.templates = manager.templates();
+        manager.tab_order = manager.tab_order();
+        manager.active_index = manager.tab_order.first().copied().unwrap_or_default();
+        manager
     }
 
-    fn parse_content(content: &str) -> Vec<Section> {
-        let chars = content.chars().collect::<Vec<char>>();
+    /// Splits the content into sections ending with a placeholder, a backslash before a
+    /// delimiter keeps it as plain text such as `\{}`.
+    fn parse_content(content: &str, options: &ParseOptions) -> Vec<Section> {
+        let delimiters = &options.delimiters;
+        let chars = content
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .chars()
+            .collect::<Vec<char>>();
         let mut sections = Vec::new();
         let mut static_txt = Vec::new();
-        for c in chars {
-            if c == '\r' {
-                static_txt.push('\n');
-            } else if c == '}' {
-                if let Some(c) = static_txt.last() {
-                    if c == &'{' {
-                        static_txt.pop();
-                        sections.push(Section::body(static_txt));
-                        static_txt = Vec::new();
-                    } else {
-                        static_txt.push(*c);
-                    }
+        let mut i = 0;
+        while i < chars.len() {
+            let rest = &chars[i..];
+            if let Some((text, len)) = delimiters.escape(rest) {
+                static_txt.extend_from_slice(text);
+                i += len;
+            } else if let Some((_, len)) = rest
+                .strip_prefix(&['\\'])
+                .and_then(|rest| SectionManager::tab_stop(rest, delimiters))
+            {
+                static_txt.extend_from_slice(&rest[1..=len]);
+                i += len + 1;
+            } else if let Some((variable, len)) = SectionManager::variable(rest, delimiters) {
+                static_txt.extend(variable.resolve().chars());
+                i += len;
+            } else if let Some((row, row_sections, len)) = SectionManager::repeat(rest, options) {
+                if !static_txt.is_empty() {
+                    sections.push(Section::tail(std::mem::take(&mut static_txt)));
                 }
+
+                let repeat = sections
+                    .iter()
+                    .filter_map(|s| s.row.as_ref())
+                    .map(|r| r.repeat + 1)
+                    .max()
+                    .unwrap_or_default();
+                let groups = sections
+                    .iter()
+                    .filter_map(|s| s.group)
+                    .max()
+                    .map_or(0, |g| g + 1);
+                sections.extend(row_sections.into_iter().map(|mut s| {
+                    s.group = s.group.map(|g| g + groups);
+                    s.in_row(Row {
+                        repeat,
+                        ..row.clone()
+                    })
+                }));
+                i += len;
+            } else if let Some((group, len)) = SectionManager::group(rest, options) {
+                if !static_txt.is_empty() {
+                    sections.push(Section::tail(std::mem::take(&mut static_txt)));
+                }
+
+                let id = sections
+                    .iter()
+                    .filter_map(|s| s.group)
+                    .max()
+                    .map_or(0, |g| g + 1);
+                let repeats = sections
+                    .iter()
+                    .filter_map(|s| s.row.as_ref())
+                    .map(|r| r.repeat + 1)
+                    .max()
+                    .unwrap_or_default();
+                sections.extend(group.into_iter().map(|mut s| {
+                    if let Some(row) = s.row.as_mut() {
+                        row.repeat += repeats;
+                    }
+                    s.in_group(id)
+                }));
+                i += len;
+            } else if let Some((placeholder, len)) = SectionManager::tab_stop(rest, delimiters)
+                .or_else(|| SectionManager::placeholder(rest, delimiters))
+            {
+                let prefix = std::mem::take(&mut static_txt);
+                sections.push(SectionManager::section(prefix, placeholder, options));
+                i += len;
             } else {
-                static_txt.push(c);
+                static_txt.push(chars[i]);
+                i += 1;
             }
         }
 
@@ -40,47 +132,572 @@ impl SectionManager {
         sections
     }
 
-    pub fn active_editable(&mut self) -> Option<&mut EditableText> {
-        let section = match self.sections.get_mut(self.active_index) {
-            Some(s) => s,
-            None => return None,
+    fn section(prefix: Vec<char>, placeholder: Placeholder, options: &ParseOptions) -> Section {
+        let section = match placeholder.name {
+            Some(name) => Section::named(prefix, &name),
+            None => Section::body(prefix),
+        };
+        let (default, error) = match (placeholder.variable, placeholder.command) {
+            (Some(variable), _) => (variable.resolve(), None),
+            (_, Some(command)) => SectionManager::run_command(&command, options),
+            _ => (placeholder.default, None),
+        };
+        let section = match placeholder.choices.is_empty() {
+            true => section.with_default(&default),
+            false => section.with_choices(placeholder.choices),
         };
 
-        section.suffix.as_mut()
+        section
+            .with_error(error)
+            .with_transform(placeholder.transform)
+            .with_validation(placeholder.validation)
     }
 
-    pub fn next_section(&mut self) -> Result<()> {
-        let next_index = self.active_index + 1;
+    /// Reads an optional group such as `{[ -H "Auth: {token}" ]}` returning its sections
+    /// along with its length, the spaces padding its inside are left out. Groups without
+    /// any placeholder inside are kept as plain text.
+    fn group(chars: &[char], options: &ParseOptions) -> Option<(Vec<Section>, usize)> {
+        let delimiters = &options.delimiters;
+        let rest = chars
+            .strip_prefix(&delimiters.open[..])?
+            .strip_prefix(&['['])?;
+        let end = (0..rest.len()).find(|i| {
+            rest[*i] == ']'
+                && rest[*i + 1..].starts_with(&delimiters.close)
+                && (*i == 0 || rest[*i - 1] != '\\')
+        })?;
 
-        // Skip the tail
-        if next_index >= self.sections.len() - 1 {
-            bail!("There is no more sections")
-        } else {
+        let content = rest[..end].iter().collect::<String>();
+        let mut sections =
+            SectionManager::parse_content(content.trim_matches([' ', '\t']), options);
+        if sections.iter().all(|s| s.suffix.is_none()) {
+            return None;
+        }
+
+        sections.retain(|s| s.suffix.is_some() || !s.prefix.is_empty());
+        let len = delimiters.open.len() + 1 + end + 1 + delimiters.close.len();
+        Some((sections, len))
+    }
+
+    /// Reads a repeat block such as `{*field: {name}: {type},\n*}` returning its name, the
+    /// separator put between its rows such as `, ` in `{*v: '{v}'*, }` and the sections of
+    /// its first row along with its length. The spaces padding a row are left out like in
+    /// groups, unless it ends with a new line and keeps its indentation. Blocks without any
+    /// placeholder inside are kept as plain text.
+    fn repeat(chars: &[char], options: &ParseOptions) -> Option<(Row, Vec<Section>, usize)> {
+        let delimiters = &options.delimiters;
+        let rest = chars
+            .strip_prefix(&delimiters.open[..])?
+            .strip_prefix(&['*'])?;
+        let colon = rest.iter().position(|c| *c == ':')?;
+        let name = &rest[..colon];
+        if name.is_empty()
+            || !name
+                .iter()
+                .all(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+        {
+            return None;
+        }
+
+        // The row ends at the first `*` followed by plain text up to the closing delimiter
+        let body = &rest[colon + 1..];
+        let (end, separator) = (0..body.len()).find_map(|i| {
+            if body[i] != '*' || (i > 0 && body[i - 1] == '\\') {
+                return None;
+            }
+            let after = &body[i + 1..];
+            let close = (0..after.len()).find(|j| after[*j..].starts_with(&delimiters.close))?;
+            let separator = &after[..close];
+            let plain = !separator.contains(&'*')
+                && !separator
+                    .windows(delimiters.open.len())
+                    .any(|w| w == &delimiters.open[..]);
+            plain.then_some((i, separator))
+        })?;
+
+        let content = body[..end].iter().collect::<String>();
+        let content = match content.ends_with('\n') {
+            true => &content,
+            false => content.trim_matches([' ', '\t']),
+        };
+        let mut sections = SectionManager::parse_content(content, options);
+        if sections.iter().all(|s| s.suffix.is_none()) {
+            return None;
+        }
+
+        sections.retain(|s| s.suffix.is_some() || !s.prefix.is_empty());
+        let row = Row {
+            name: name.iter().collect(),
+            separator: separator.iter().collect(),
+            repeat: 0,
+            index: 0,
+        };
+        let len = delimiters.open.len()
+            + 1
+            + colon
+            + 1
+            + end
+            + 1
+            + separator.len()
+            + delimiters.close.len();
+        Some((row, sections, len))
+    }
+
+    /// Runs a placeholder's command returning its output, or the error to show in its place.
+    fn run_command(command: &str, options: &ParseOptions) -> (String, Option<String>) {
+        if !options.allow_commands {
+            let error =
+                format!("Not running `{command}`, commands are only run with --allow-commands");
+            return (String::new(), Some(error));
+        }
+
+        match command::run(command, options.command_timeout) {
+            Ok(output) => (output, None),
+            Err(e) => (String::new(), Some(e.to_string())),
+        }
+    }
+
+    /// Reads a placeholder starting with an opening delimiter, returning it along with its
+    /// length including the delimiters.
+    fn placeholder(chars: &[char], delimiters: &Delimiters) -> Option<(Placeholder, usize)> {
+        let (content, len) = SectionManager::delimited(chars, delimiters)?;
+        Placeholder::parse(&content).map(|p| (p, len))
+    }
+
+    /// Reads a built-in variable such as `{$DATE}`, returning it along with its length.
+    fn variable(chars: &[char], delimiters: &Delimiters) -> Option<(Variable, usize)> {
+        let (content, len) = SectionManager::delimited(chars, delimiters)?;
+        let name = content.strip_prefix(&['$'])?.iter().collect::<String>();
+        Variable::parse(&name).map(|v| (v, len))
+    }
+
+    /// Reads a numbered tab-stop such as `${1}` or `${2:default}`, returning it along with
+    /// its length. A bare `$1` is left as text since shell and awk snippets are full of them.
+    fn tab_stop(chars: &[char], delimiters: &Delimiters) -> Option<(Placeholder, usize)> {
+        let rest = chars.strip_prefix(&['$'])?;
+        let (placeholder, len) = SectionManager::placeholder(rest, delimiters)?;
+        let numbered = placeholder
+            .name
+            .as_ref()
+            .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()));
+        numbered.then_some((placeholder, len + 1))
+    }
+
+    /// Reads the unescaped content between an opening and closing delimiter, returning it
+    /// along with its length including the delimiters. Delimiters inside a regex type are
+    /// kept as long as they are balanced, so `{zip!regex=^\d{5}$}` ends at its last brace.
+    fn delimited(chars: &[char], delimiters: &Delimiters) -> Option<(Vec<char>, usize)> {
+        let chars = chars.strip_prefix(&delimiters.open[..])?;
+        let mut content = Vec::new();
+        let mut depth = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let rest = &chars[i..];
+            if let Some(escaped) = delimiters.escaped(rest) {
+                content.extend_from_slice(escaped);
+                i += escaped.len() + 1;
+            } else if rest.starts_with(&delimiters.close) && depth > 0 {
+                content.extend_from_slice(&delimiters.close);
+                depth -= 1;
+                i += delimiters.close.len();
+            } else if rest.starts_with(&delimiters.close) {
+                let len = delimiters.open.len() + i + delimiters.close.len();
+                return Some((content, len));
+            } else if rest.starts_with(&delimiters.open) && Placeholder::starts_regex(&content) {
+                content.extend_from_slice(&delimiters.open);
+                depth += 1;
+                i += delimiters.open.len();
+            } else {
+                content.push(chars[i]);
+                i += 1;
+            }
+        }
+
+        None
+    }
+
+    /// A default, choice or type written on any occurrence of a named placeholder applies to all of them.
+    fn share_defaults(&mut self) {
+        let mut defining: Vec<usize> = Vec::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            let (Some(_), Some(editable)) = (&section.name, &section.suffix) else {
+                continue;
+            };
+
+            let defined = editable.is_selected()
+                || section.choice.is_some()
+                || section.error.is_some()
+                || section.validation.is_some();
+            if defined && !defining.iter().any(|d| self.sections[*d].mirrors(section)) {
+                defining.push(i);
+            }
+        }
+
+        for d in defining {
+            let source = self.sections[d].clone();
+            self.sections
+                .iter_mut()
+                .filter(|s| s.mirrors(&source))
+                .for_each(|s| {
+                    s.suffix = source.suffix.clone();
+                    s.choice = source.choice.clone();
+                    s.error = source.error.clone();
+                    s.validation = source.validation.clone();
+                });
+        }
+    }
+
+    pub fn active_section(&mut self) -> Option<&mut Section> {
+        self.sections.get_mut(self.active_index)
+    }
+
+    pub fn active_editable(&mut self) -> Option<&mut EditableText> {
+        self.sections.get_mut(self.active_index)?.suffix.as_mut()
+    }
+
+    /// Indices of the sections in the order the user navigates through them. Placeholders
+    /// sharing a name are a single field, so only one occurrence is visited, preferably
+    /// one without a transform so the value is typed as it is.
+    /// Numbered tab-stops come first by number, followed by the rest in order of
+    /// appearance, and `$0` is always visited last.
+    fn tab_order(&self) -> Vec<usize> {
+        let mut stops: Vec<usize> = Vec::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            if section.suffix.is_none() {
+                continue;
+            }
+
+            if section.name.is_none() {
+                stops.push(i);
+                continue;
+            }
+
+            match stops
+                .iter()
+                .position(|s| self.sections[*s].mirrors(section))
+            {
+                Some(p)
+                    if self.sections[stops[p]].transform.is_some()
+                        && section.transform.is_none() =>
+                {
+                    stops[p] = i
+                }
+                Some(_) => (),
+                None => stops.push(i),
+            }
+        }
+
+        stops.sort();
+        // Numbers inside repeat blocks would mix up their rows, so those keep their place
+        stops.sort_by_key(|i| {
+            let section = &self.sections[*i];
+            match section.tab_stop().filter(|_| section.row.is_none()) {
+                Some(0) => (2, 0),
+                Some(number) => (0, number),
+                None => (1, 0),
+            }
+        });
+        stops
+    }
+
+    /// Copies the active field's value into every other placeholder with the same name.
+    pub fn sync_mirrors(&mut self) {
+        if self.sections.get(self.active_index).is_some() {
+            self.copy_to_mirrors(self.active_index);
+        }
+    }
+
+    /// Copies the value of a field, and its selected choice, to the placeholders mirroring it.
+    fn copy_to_mirrors(&mut self, index: usize) {
+        let source = self.sections[index].clone();
+        for section in self.sections.iter_mut().filter(|s| s.mirrors(&source)) {
+            section.suffix = source.suffix.clone();
+            if source.choice.is_some() {
+                section.choice = source.choice.clone();
+            }
+        }
+    }
+
+    /// Position in the navigation order of the first field whose value is not valid,
+    /// fields of a dropped group don't need one.
+    pub fn first_invalid(&self) -> Option<usize> {
+        self.tab_order.iter().position(|i| self.invalid(*i))
+    }
+
+    fn invalid(&self, index: usize) -> bool {
+        let section = &self.sections[index];
+        !self.is_dropped(section) && section.validation_error().is_some()
+    }
+
+    /// Moves to the field at the given position in the navigation order.
+    pub fn focus(&mut self, position: usize) {
+        if let Some(index) = self.tab_order.get(position).copied() {
             if let Some(e) = self.active_editable() {
                 e.cursor_to_right_edge()
             }
-            self.active_index = next_index;
-            Ok(())
+            self.position = position;
+            self.active_index = index;
+        }
+    }
+
+    pub fn next_section(&mut self) -> Result<()> {
+        match self.tab_order.get(self.position + 1).copied() {
+            Some(next_index) => {
+                if let Some(e) = self.active_editable() {
+                    e.cursor_to_right_edge()
+                }
+                self.position += 1;
+                self.active_index = next_index;
+                Ok(())
+            }
+            None => bail!("There is no more sections"),
         }
     }
 
     pub fn previous_section(&mut self) -> Result<()> {
-        if self.active_index > 0 {
-            self.active_index -= 1;
+        if self.position > 0 {
+            self.position -= 1;
+            self.active_index = self.tab_order[self.position];
             Ok(())
         } else {
             bail!("Cannot go bellow zero")
         }
     }
 
+    /// The sections of the first row of every repeat block, used to add new rows.
+    fn templates(&self) -> Vec<Vec<Section>> {
+        let mut templates: Vec<Vec<Section>> = Vec::new();
+        for section in &self.sections {
+            let Some(row) = &section.row else {
+                continue;
+            };
+
+            if templates.len() <= row.repeat {
+                templates.resize_with(row.repeat + 1, Vec::new);
+            }
+            templates[row.repeat].push(section.clone());
+        }
+
+        templates
+    }
+
+    /// Adds a row after the active one in a repeat block, and moves to its first field.
+    pub fn add_row(&mut self) -> Result<()> {
+        let Some(row) = self
+            .sections
+            .get(self.active_index)
+            .and_then(|s| s.row.clone())
+        else {
+            bail!("The active placeholder is not in a repeat block")
+        };
+
+        let end = self
+            .sections
+            .iter()
+            .rposition(|s| s.row.as_ref() == Some(&row))
+            .map_or(self.active_index, |i| i + 1);
+        let index = self
+            .sections
+            .iter()
+            .filter_map(|s| s.row.as_ref())
+            .filter(|r| r.repeat == row.repeat)
+            .map(|r| r.index + 1)
+            .max()
+            .unwrap_or_default();
+        // Optional groups of the new row are separate from the ones of other rows
+        let groups = self
+            .sections
+            .iter()
+            .filter_map(|s| s.group)
+            .max()
+            .map_or(0, |g| g + 1);
+        let new_row = self.templates[row.repeat].iter().cloned().map(|mut s| {
+            s.group = s.group.map(|g| g + groups);
+            s.row = s.row.map(|r| Row { index, ..r });
+            s
+        });
+
+        self.sections.splice(end..end, new_row);
+        self.tab_order = self.tab_order();
+        let position = self.tab_order.iter().position(|i| {
+            self.sections[*i]
+                .row
+                .as_ref()
+                .is_some_and(|r| r.repeat == row.repeat && r.index == index)
+        });
+        self.focus(position.unwrap_or_default());
+        Ok(())
+    }
+
+    /// Removes the active row of a repeat block, every block keeps at least one row.
+    pub fn remove_row(&mut self) -> Result<()> {
+        let Some(row) = self
+            .sections
+            .get(self.active_index)
+            .and_then(|s| s.row.clone())
+        else {
+            bail!("The active placeholder is not in a repeat block")
+        };
+
+        let other_rows = self
+            .sections
+            .iter()
+            .filter_map(|s| s.row.as_ref())
+            .any(|r| r.repeat == row.repeat && r.index != row.index);
+        if !other_rows {
+            bail!("The last row of {} cannot be removed", row.name)
+        }
+
+        let start = self
+            .sections
+            .iter()
+            .position(|s| s.row.as_ref() == Some(&row))
+            .unwrap_or_default();
+        self.sections.retain(|s| s.row.as_ref() != Some(&row));
+        self.tab_order = self.tab_order();
+
+        // The following row takes the place of the removed one, or else the previous field
+        self.position = self
+            .tab_order
+            .iter()
+            .position(|i| {
+                *i >= start
+                    && self.sections[*i]
+                        .row
+                        .as_ref()
+                        .is_some_and(|r| r.repeat == row.repeat)
+            })
+            .or_else(|| self.tab_order.iter().rposition(|i| *i < start))
+            .unwrap_or_default();
+        self.active_index = self
+            .tab_order
+            .get(self.position)
+            .copied()
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    /// Whether the section belongs to an optional group whose placeholders are all empty,
+    /// such a group is left out of the final text.
+    pub fn is_dropped(&self, section: &Section) -> bool {
+        let Some(group) = section.group else {
+            return false;
+        };
+
+        self.sections
+            .iter()
+            .filter(|s| s.group == Some(group) && s.suffix.is_some())
+            .all(|s| s.value().is_empty())
+    }
+
+    /// The separator of a repeat block put before the section, when it starts a row
+    /// following another one.
+    pub fn separator(&self, index: usize) -> Option<&str> {
+        let row = self.sections[index].row.as_ref()?;
+        let previous = self.sections[..index].last()?.row.as_ref()?;
+        let follows = previous.repeat == row.repeat && previous.index != row.index;
+        follows.then_some(row.separator.as_str())
+    }
+
+    /// Fills in the field with the given name such as `name` or `2` for `${2}`, or the one
+    /// at a position of the navigation order counted from 1 such as `#2`, along with the
+    /// placeholders mirroring it. A number without `#` is a position when no tab-stop has it.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let numbered = |number: &str| {
+            number
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=self.tab_order.len()).contains(n))
+                .map(|n| n - 1)
+        };
+        let position = match key.strip_prefix('#') {
+            Some(number) => numbered(number),
+            None => self
+                .tab_order
+                .iter()
+                .position(|i| self.sections[*i].name.as_deref() == Some(key))
+                .or_else(|| numbered(key)),
+        };
+        let Some(position) = position else {
+            bail!("The snippet has no placeholder {key:?}")
+        };
+
+        let index = self.tab_order[position];
+        let section = &mut self.sections[index];
+        if let Some(choice) = &mut section.choice {
+            if !choice.select(value) {
+                bail!("{value:?} is not one of the choices of {key:?}")
+            }
+        }
+        section.suffix = Some(EditableText::with_default(value));
+        self.copy_to_mirrors(index);
+        Ok(())
+    }
+
+    /// Fields still missing a value, named or numbered as they are given to `set`. Empty
+    /// fields of optional groups and `$0` can be left out, while a value not matching its
+    /// type is missing as well.
+    pub fn unfilled(&self) -> Vec<String> {
+        self.tab_order
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| {
+                let section = &self.sections[**i];
+                let empty = section
+                    .suffix
+                    .as_ref()
+                    .is_some_and(|e| e.chars().is_empty());
+                let optional = section.group.is_some() || section.tab_stop() == Some(0);
+                (empty && !optional) || self.invalid(**i)
+            })
+            .map(|(position, i)| match &self.sections[*i].name {
+                Some(name) => name.clone(),
+                None => format!("#{}", position + 1),
+            })
+            .collect()
+    }
+
+    /// The filled in snippet, a dropped group takes one of the spaces around it along so
+    /// `curl {[-H {header}]} {url}` doesn't end up with two of them.
     pub fn text(&self) -> String {
-        self.sections.iter().map(|s| s.text()).collect()
+        let mut text = String::new();
+        let mut dropped = false;
+        for (i, section) in self.sections.iter().enumerate() {
+            if let Some(separator) = self.separator(i) {
+                text.push_str(separator);
+            }
+            if self.is_dropped(section) {
+                dropped = true;
+                continue;
+            }
+
+            let mut part = section.text();
+            if dropped && !part.is_empty() {
+                let gap = text.is_empty() || text.ends_with([' ', '\t', '\n']);
+                if gap && part.starts_with([' ', '\t']) {
+                    part.remove(0);
+                } else if part.starts_with('\n') {
+                    text.truncate(text.trim_end_matches([' ', '\t']).len());
+                }
+                dropped = false;
+            }
+            text.push_str(&part);
+        }
+
+        if dropped {
+            text.truncate(text.trim_end_matches([' ', '\t']).len());
+        }
+        text
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::sections::{section::Section, section_manager::SectionManager};
+    use crate::sections::{
+        parse_options::{ParseOptions, Syntax},
+        section::{Row, Section},
+        section_manager::SectionManager,
+    };
 
     #[test]
     fn return_string_as_section_tail() {
@@ -183,11 +800,729 @@ mod test {
         assert_eq!(manager.active_editable().unwrap().insertion_position(), 1);
     }
 
+    #[test]
+    fn parse_named_sections() {
+        let manager = SectionManager::new("struct {name} {} impl {name} {}");
+        assert_eq!(5, manager.sections.len());
+        assert_eq!(manager.sections[0], section_named("struct ", "name"));
+        assert_eq!(manager.sections[1], section_body(" "));
+        assert_eq!(manager.sections[2], section_named(" impl ", "name"));
+        assert_eq!(manager.sections[3], section_body(" "));
+        assert_eq!(manager.sections[4], section_tail(""));
+    }
+
+    #[test]
+    fn invalid_names_are_kept_as_text() {
+        let manager = SectionManager::new("{ \"key\": 1 } {");
+        assert_eq!(1, manager.sections.len());
+        assert_eq!(manager.sections[0], section_tail("{ \"key\": 1 } {"));
+    }
+
+    #[test]
+    fn navigation_visits_each_name_once() {
+        let mut manager = SectionManager::new("{a} {b} {a} {} {b}");
+        assert_eq!(manager.active_index, 0);
+        assert!(manager.next_section().is_ok());
+        assert_eq!(manager.active_index, 1);
+        assert!(manager.next_section().is_ok());
+        assert_eq!(manager.active_index, 3);
+        assert!(manager.next_section().is_err());
+        assert!(manager.previous_section().is_ok());
+        assert_eq!(manager.active_index, 1);
+    }
+
+    #[test]
+    fn mirrors_named_sections() {
+        let mut manager = SectionManager::new("let {var} = 1; {var} += {};");
+        "count"
+            .chars()
+            .for_each(|c| manager.active_editable().unwrap().insert(c));
+        manager.sync_mirrors();
+        assert_eq!("let count = 1; count += ;".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let manager = SectionManager::new("host {host:localhost} port {:8080}");
+        assert_eq!(3, manager.sections.len());
+        assert_eq!(
+            manager.sections[0],
+            section_named("host ", "host").with_default("localhost")
+        );
+        assert_eq!(
+            manager.sections[1],
+            section_body(" port ").with_default("8080")
+        );
+        assert_eq!("host localhost port 8080".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn default_is_shared_between_mirrors() {
+        let manager = SectionManager::new("{port} {port:8080}");
+        assert_eq!("8080 8080".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn typing_replaces_default() {
+        let mut manager = SectionManager::new("{port:8080} {port}");
+        manager.active_editable().unwrap().insert('9');
+        manager.sync_mirrors();
+        assert_eq!("9 9".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn parse_choices() {
+        let manager = SectionManager::new("curl -X {method|GET,POST} {url}");
+        assert_eq!(3, manager.sections.len());
+        assert_eq!(
+            manager.sections[0],
+            section_named("curl -X ", "method").with_choices(vec!["GET".into(), "POST".into()])
+        );
+        assert_eq!("curl -X GET ".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn choices_are_mirrored() {
+        let mut manager = SectionManager::new("{level|info,warn} {level}");
+        manager.active_section().unwrap().choose(|c| c.next());
+        manager.sync_mirrors();
+        assert_eq!("warn warn".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn choices_are_shared_with_earlier_mirrors() {
+        let mut manager = SectionManager::new("{level} {level|info,warn}");
+        assert_eq!("info info".to_owned(), manager.text());
+        assert!(manager.active_section().unwrap().choice.is_some());
+    }
+
+    #[test]
+    fn placeholder_only() {
+        let manager = SectionManager::new("{}");
+        assert_eq!(manager.sections, vec![section_body(""), section_tail("")]);
+    }
+
+    #[test]
+    fn adjacent_placeholders() {
+        let manager = SectionManager::new("{}{}");
+        assert_eq!(
+            manager.sections,
+            vec![section_body(""), section_body(""), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn lone_closing_brace_is_kept() {
+        let manager = SectionManager::new("a} }b");
+        assert_eq!(manager.sections, vec![section_tail("a} }b")]);
+    }
+
+    #[test]
+    fn closing_brace_at_start_is_kept() {
+        let manager = SectionManager::new("} {}");
+        assert_eq!(manager.sections, vec![section_body("} "), section_tail("")]);
+    }
+
+    #[test]
+    fn unclosed_brace_is_kept() {
+        let manager = SectionManager::new("fn main() { {}");
+        assert_eq!(
+            manager.sections,
+            vec![section_body("fn main() { "), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn nested_braces() {
+        let manager = SectionManager::new("{{}}");
+        assert_eq!(manager.sections, vec![section_body("{"), section_tail("}")]);
+    }
+
+    #[test]
+    fn replaces_windows_line_endings_once() {
+        let manager = SectionManager::new("one\r\ntwo\r\n");
+        assert_eq!(manager.sections, vec![section_tail("one\ntwo\n")]);
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        let manager = SectionManager::new("let map = \\{\\}; \\{name} {}");
+        assert_eq!(
+            manager.sections,
+            vec![section_body("let map = {}; {name} "), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn escaped_backslash_before_placeholder() {
+        let manager = SectionManager::new("C:\\\\{dir}");
+        assert_eq!(
+            manager.sections,
+            vec![section_named("C:\\", "dir"), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn other_backslashes_are_kept() {
+        let manager = SectionManager::new("echo \"a\\tb\\n\" \\\\ {}");
+        assert_eq!(
+            manager.sections,
+            vec![section_body("echo \"a\\tb\\n\" \\\\ "), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn escaped_braces_inside_default() {
+        let manager = SectionManager::new("{body:\\{\\}}");
+        assert_eq!("{}".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn alternate_delimiters() {
+        let manager = SectionManager::with_options("{} ${name} $ {x} ${:1}", &delimited("${ }"));
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_named("{} ", "name"),
+                section_body(" $ {x} ").with_default("1"),
+                section_tail("")
+            ]
+        );
+    }
+
+    #[test]
+    fn alternate_multi_character_delimiters() {
+        let manager =
+            SectionManager::with_options("a <<b> <<name>> \\<<x>> <<>>>", &delimited("<<>>"));
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_named("a <<b> ", "name"),
+                section_body(" <<x>> "),
+                section_tail(">")
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_tab_stops() {
+        let manager = SectionManager::new("${1} ${2} ${3:default} ${10}");
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_named("", "1"),
+                section_named(" ", "2"),
+                section_named(" ", "3").with_default("default"),
+                section_named(" ", "10"),
+                section_tail("")
+            ]
+        );
+    }
+
+    #[test]
+    fn dollar_without_tab_stop_is_kept() {
+        let manager = SectionManager::new("$ $a $$ ${name}");
+        assert_eq!(
+            manager.sections,
+            vec![section_named("$ $a $$ $", "name"), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn bare_dollar_numbers_are_text() {
+        let manager = SectionManager::new("awk '{print $1}' {f} $0");
+        assert_eq!(
+            manager.sections,
+            vec![section_named("awk '{print $1}' ", "f"), section_tail(" $0")]
+        );
+    }
+
+    #[test]
+    fn escaped_tab_stops_are_literal() {
+        let manager = SectionManager::new("echo \\$1 \\${2} \\$HOME");
+        assert_eq!(
+            manager.sections,
+            vec![section_tail("echo \\$1 ${2} \\$HOME")]
+        );
+    }
+
+    #[test]
+    fn visits_tab_stops_in_order() {
+        let mut manager = SectionManager::new("${0} {a} ${2} {} ${1} ${2}");
+        assert_eq!(manager.active_index, 4);
+        let mut visited = vec![manager.active_index];
+        while manager.next_section().is_ok() {
+            visited.push(manager.active_index);
+        }
+        assert_eq!(visited, vec![4, 2, 1, 3, 0]);
+
+        assert!(manager.previous_section().is_ok());
+        assert_eq!(manager.active_index, 3);
+    }
+
+    #[test]
+    fn tab_stops_are_mirrored() {
+        let mut manager = SectionManager::new("${1:a} ${1}");
+        manager.active_editable().unwrap().insert('b');
+        manager.sync_mirrors();
+        assert_eq!("b b".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn resolves_variables_as_text() {
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        let manager = SectionManager::new("cd {$CWD} && {}");
+        assert_eq!(
+            manager.sections,
+            vec![section_body(&format!("cd {cwd} && ")), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn resolves_variables_as_default() {
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        let manager = SectionManager::new("cd {dir:$CWD}");
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_named("cd ", "dir").with_default(&cwd),
+                section_tail("")
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_variables_are_kept() {
+        let manager = SectionManager::new("{$HOME} {$ENV:}");
+        assert_eq!(manager.sections, vec![section_tail("{$HOME} {$ENV:}")]);
+    }
+
+    #[test]
+    fn commands_are_not_run_by_default() {
+        let manager = SectionManager::new("branch {$(echo main)}");
+        let section = &manager.sections[0];
+        assert_eq!("branch ".to_owned(), manager.text());
+        assert!(section.error.as_ref().unwrap().contains("--allow-commands"));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn runs_allowed_commands() {
+        let options = ParseOptions {
+            allow_commands: true,
+            ..ParseOptions::default()
+        };
+        let manager = SectionManager::with_options("branch {name:$(echo main)} {name}", &options);
+        assert_eq!(
+            manager.sections[0],
+            section_named("branch ", "name").with_default("main")
+        );
+        assert_eq!("branch main main".to_owned(), manager.text());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn failed_commands_show_error() {
+        let options = ParseOptions {
+            allow_commands: true,
+            ..ParseOptions::default()
+        };
+        let manager = SectionManager::with_options("{$(echo broken >&2; exit 1)}", &options);
+        assert_eq!("".to_owned(), manager.text());
+        assert!(manager.sections[0]
+            .error
+            .as_ref()
+            .unwrap()
+            .ends_with("failed: broken"));
+    }
+
+    #[test]
+    fn transforms_mirrors() {
+        let mut manager = SectionManager::new("mod {name/snake};\nstruct {name} {}");
+        assert_eq!(manager.active_index, 1);
+        "HttpClient"
+            .chars()
+            .for_each(|c| manager.active_editable().unwrap().insert(c));
+        manager.sync_mirrors();
+        assert_eq!(
+            "mod http_client;\nstruct HttpClient ".to_owned(),
+            manager.text()
+        );
+    }
+
+    #[test]
+    fn only_transformed_occurrences_are_visited_once() {
+        let mut manager = SectionManager::new("{name/upper} {name/lower}");
+        assert_eq!(manager.active_index, 0);
+        manager.active_editable().unwrap().insert('a');
+        manager.sync_mirrors();
+        assert_eq!("A a".to_owned(), manager.text());
+        assert!(manager.next_section().is_err());
+    }
+
+    #[test]
+    fn transformed_first_occurrence_keeps_order() {
+        let mut manager = SectionManager::new("{name/snake} {} {name}");
+        assert_eq!(manager.active_index, 1);
+        assert!(manager.next_section().is_ok());
+        assert_eq!(manager.active_index, 2);
+    }
+
+    #[test]
+    fn finds_invalid_fields() {
+        let mut manager = SectionManager::new("{a!int:1} {b!int} {a}");
+        assert_eq!(manager.first_invalid(), Some(1));
+        manager.focus(1);
+        assert_eq!(manager.active_index, 1);
+        manager.active_editable().unwrap().insert('2');
+        assert_eq!(manager.first_invalid(), None);
+    }
+
+    #[test]
+    fn regex_types_keep_balanced_braces() {
+        let mut manager = SectionManager::new("zip {zip!regex=^\\d{5}$} {x}");
+        assert_eq!(manager.sections.len(), 3);
+        manager.set("zip", "1234").unwrap();
+        assert_eq!(manager.first_invalid(), Some(0));
+        manager.set("zip", "12345").unwrap();
+        assert_eq!(manager.first_invalid(), None);
+        assert_eq!("zip 12345 ".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn types_are_shared_with_mirrors() {
+        let manager = SectionManager::new("{a} {a!int}");
+        assert!(manager.sections[0].validation.is_some());
+        assert_eq!(manager.first_invalid(), Some(0));
+    }
+
+    #[test]
+    fn parse_optional_group() {
+        let manager = SectionManager::new("curl {[ -H \"Auth: {token}\" ]} {url}");
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_tail("curl "),
+                section_named("-H \"Auth: ", "token").in_group(0),
+                section_tail("\"").in_group(0),
+                section_named(" ", "url"),
+                section_tail("")
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_empty_groups() {
+        let mut manager = SectionManager::new("curl {[ -H \"Auth: {token}\" ]} {url}");
+        assert!(manager.next_section().is_ok());
+        "example.com"
+            .chars()
+            .for_each(|c| manager.active_editable().unwrap().insert(c));
+        assert_eq!("curl example.com".to_owned(), manager.text());
+
+        assert!(manager.previous_section().is_ok());
+        manager.active_editable().unwrap().insert('t');
+        assert_eq!("curl -H \"Auth: t\" example.com".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn dropped_groups_take_their_padding() {
+        let manager = SectionManager::new("ls {[ -I {a} ]} {[ -w {b} ]} .\nrm {[ -{c} ]}\n");
+        assert_eq!("ls .\nrm\n".to_owned(), manager.text());
+        let manager = SectionManager::new("{[{a} ]} x");
+        assert_eq!("x".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn dropped_groups_need_no_valid_value() {
+        let mut manager = SectionManager::new("curl {[-n {count!int}]} {url}");
+        manager.set("url", "x").unwrap();
+        assert_eq!(manager.first_invalid(), None);
+        assert!(manager.unfilled().is_empty());
+        assert_eq!("curl x".to_owned(), manager.text());
+
+        manager.set("count", "many").unwrap();
+        assert_eq!(manager.first_invalid(), Some(0));
+        assert_eq!(manager.unfilled(), vec!["count"]);
+    }
+
+    #[test]
+    fn group_is_kept_when_any_placeholder_is_filled() {
+        let manager = SectionManager::new("{[{a} {b:x}]}");
+        assert_eq!(" x".to_owned(), manager.text());
+        let manager = SectionManager::new("a {[{b} {c}]}!");
+        assert_eq!("a !".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn shell_tests_are_kept() {
+        let manager = SectionManager::new("if [[ -f {file} ]]; then [[ -d x ]] fi");
+        assert_eq!(manager.sections[0], section_named("if [[ -f ", "file"));
+        assert_eq!(
+            "if [[ -f  ]]; then [[ -d x ]] fi".to_owned(),
+            manager.text()
+        );
+    }
+
+    #[test]
+    fn groups_follow_the_delimiters() {
+        let manager =
+            SectionManager::with_options("echo <<[ -{a} <<b>> ]>> {[x]}", &delimited("<< >>"));
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_tail("echo "),
+                section_named("-{a} ", "b").in_group(0),
+                section_tail(" {[x]}"),
+            ]
+        );
+        assert_eq!("echo {[x]}".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn escaped_groups_are_literal() {
+        let manager = SectionManager::new("\\{[{a}]} {[{b}]}");
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_named("{[", "a"),
+                section_tail("]} "),
+                section_named("", "b").in_group(0),
+                section_tail(""),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_group_is_kept() {
+        let manager = SectionManager::new("{[{a}");
+        assert_eq!(
+            manager.sections,
+            vec![section_named("{[", "a"), section_tail("")]
+        );
+    }
+
+    #[test]
+    fn parse_repeat() {
+        let manager = SectionManager::new("IN ({*id:'{id}'*, })");
+        let row = Row {
+            separator: ", ".to_owned(),
+            ..row("id", 0)
+        };
+        assert_eq!(
+            manager.sections,
+            vec![
+                section_tail("IN ("),
+                section_named("'", "id").in_row(row.clone()),
+                section_tail("'").in_row(row),
+                section_tail(")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn separates_rows() {
+        let mut manager = SectionManager::new("IN ({*v:'{x}'*, }) AND {*c: {col} *}");
+        manager.active_editable().unwrap().insert('a');
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('b');
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('c');
+        assert_eq!(manager.text(), "IN ('a', 'b', 'c') AND ");
+
+        manager.previous_section().unwrap();
+        manager.previous_section().unwrap();
+        manager.remove_row().unwrap();
+        assert_eq!(manager.text(), "IN ('b', 'c') AND ");
+    }
+
+    #[test]
+    fn rows_of_lines_keep_their_indentation() {
+        let manager = SectionManager::new("SELECT {*c: {col}*, } {*f:  {x}\n*}");
+        assert!(manager.sections[1].prefix.is_empty());
+        assert_eq!(manager.sections[3].prefix, vec![' ', ' ']);
+    }
+
+    #[test]
+    fn repeat_without_placeholders_is_kept() {
+        let manager = SectionManager::new("{*a:b*} {*:{c}*}");
+        assert_eq!(manager.sections[0], section_named("{*a:b*} {*:", "c"));
+    }
+
+    #[test]
+    fn adds_rows_after_active_one() {
+        let mut manager = SectionManager::new("{*env:ENV {key}={value:x}\n*}RUN {cmd}");
+        manager.active_editable().unwrap().insert('A');
+        manager.add_row().unwrap();
+        assert_eq!(manager.active_index, 3);
+        manager.active_editable().unwrap().insert('B');
+        assert_eq!("ENV A=x\nENV B=x\nRUN ".to_owned(), manager.text());
+
+        manager.previous_section().unwrap();
+        manager.previous_section().unwrap();
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('C');
+        assert_eq!("ENV A=x\nENV C=x\nENV B=x\nRUN ".to_owned(), manager.text());
+        assert!(manager.next_section().is_ok());
+        assert!(manager.next_section().is_ok());
+        assert_eq!(manager.active_index, 6);
+    }
+
+    #[test]
+    fn names_are_scoped_per_row() {
+        let mut manager = SectionManager::new("{name} {*f:{name}/{name}* }");
+        manager.active_editable().unwrap().insert('a');
+        manager.sync_mirrors();
+        manager.next_section().unwrap();
+        manager.active_editable().unwrap().insert('b');
+        manager.sync_mirrors();
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('c');
+        manager.sync_mirrors();
+        assert_eq!("a b/b c/c".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn removes_active_row() {
+        let mut manager = SectionManager::new("{a} {*f:{x},*} {b}");
+        assert!(manager.remove_row().is_err());
+        manager.next_section().unwrap();
+        assert!(manager.remove_row().is_err());
+
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('2');
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('3');
+        manager.previous_section().unwrap();
+        manager.previous_section().unwrap();
+        manager.remove_row().unwrap();
+        assert_eq!(" 2,3, ".to_owned(), manager.text());
+        assert_eq!(manager.active_index, 2);
+        assert_eq!(manager.active_section().unwrap().value(), "2");
+
+        manager.next_section().unwrap();
+        manager.remove_row().unwrap();
+        assert_eq!(manager.active_index, 2);
+        manager.next_section().unwrap();
+        assert_eq!(manager.active_index, 4);
+        assert!(manager.next_section().is_err());
+    }
+
+    #[test]
+    fn rows_keep_their_own_groups() {
+        let mut manager = SectionManager::new("{*h:curl {[-H {header}]} {url}\n*}");
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('x');
+        assert_eq!("curl \ncurl -H x \n".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn repeats_in_groups_keep_their_own_rows() {
+        let mut manager = SectionManager::new("{*a:{x}*,} {[ -v {*b:{y}*,} ]}");
+        let repeats = manager
+            .sections
+            .iter()
+            .filter_map(|s| s.row.as_ref().map(|r| r.repeat))
+            .collect::<Vec<_>>();
+        assert_eq!(repeats, vec![0, 1]);
+
+        manager.active_editable().unwrap().insert('1');
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('2');
+        manager.next_section().unwrap();
+        manager.active_editable().unwrap().insert('3');
+        manager.add_row().unwrap();
+        manager.active_editable().unwrap().insert('4');
+        assert_eq!("1,2 -v 3,4".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn tab_stops_in_rows_keep_their_place() {
+        let manager = SectionManager::new("${2} {*r:${1}* }${1}");
+        assert_eq!(manager.tab_order, vec![3, 0, 2]);
+    }
+
+    #[test]
+    fn parses_textmate_syntax() {
+        let options = ParseOptions {
+            syntax: Syntax::TextMate,
+            ..ParseOptions::default()
+        };
+        let mut manager =
+            SectionManager::with_options("{ \"${1:key}\": $0, \"$1\": ${2:int} }", &options);
+        assert_eq!(manager.tab_order, vec![0, 3, 1]);
+        manager.active_editable().unwrap().insert('k');
+        manager.sync_mirrors();
+        assert_eq!("{ \"k\": , \"k\": int }".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn sets_fields_by_name_or_position() {
+        let mut manager =
+            SectionManager::new("{greeting|hi,hello} {name}, {name/upper} {} {[({note})]}");
+        manager.set("name", "ada").unwrap();
+        manager.set("#3", "!").unwrap();
+        manager.set("greeting", "hello").unwrap();
+        assert_eq!("hello ada, ADA !".to_owned(), manager.text());
+        assert!(manager.set("greeting", "hey").is_err());
+        assert!(manager.set("missing", "x").is_err());
+        assert!(manager.set("#5", "x").is_err());
+        assert!(manager.set("5", "x").is_err());
+        manager.set("3", "?").unwrap();
+        assert_eq!("hello ada, ADA ?".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn tab_stops_and_positions_have_their_own_keys() {
+        let mut manager = SectionManager::new("${2} {}");
+        assert_eq!(manager.unfilled(), vec!["2", "#2"]);
+        manager.set("#1", "a").unwrap();
+        manager.set("#2", "b").unwrap();
+        assert!(manager.unfilled().is_empty());
+        manager.set("2", "c").unwrap();
+        assert_eq!("c b".to_owned(), manager.text());
+        manager.set("1", "d").unwrap();
+        assert_eq!("d b".to_owned(), manager.text());
+    }
+
+    #[test]
+    fn lists_unfilled_fields() {
+        let mut manager =
+            SectionManager::new("retry {count!int} times in {} as {user:$USER} {[{x}]}");
+        manager.set("user", "root").unwrap();
+        assert_eq!(manager.unfilled(), vec!["count", "#2"]);
+        manager.set("count", "many").unwrap();
+        manager.set("#2", "ms").unwrap();
+        assert_eq!(manager.unfilled(), vec!["count"]);
+        manager.set("#1", "3").unwrap();
+        assert!(manager.unfilled().is_empty());
+    }
+
+    fn delimited(delimiters: &str) -> ParseOptions {
+        ParseOptions {
+            delimiters: delimiters.parse().unwrap(),
+            ..ParseOptions::default()
+        }
+    }
+
     fn section_body(str: &str) -> Section {
         Section::body(str.chars().collect())
     }
 
+    fn section_named(str: &str, name: &str) -> Section {
+        Section::named(str.chars().collect(), name)
+    }
+
     fn section_tail(str: &str) -> Section {
         Section::tail(str.chars().collect())
     }
+
+    fn row(name: &str, index: usize) -> Row {
+        Row {
+            name: name.to_owned(),
+            separator: String::new(),
+            repeat: 0,
+            index,
+        }
+    }
 }
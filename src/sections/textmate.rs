@@ -0,0 +1,365 @@
+use super::{section::Section, transform::Transform, variables::Variable};
+
+/// Part of a snippet written in the TextMate syntax used by VS Code snippets.
+#[derive(Debug, PartialEq)]
+enum Element {
+    Text(char),
+    /// A tab-stop such as `$1`, `${1:default}`, `${1|one,two|}` or `${1/regex/format/}`.
+    TabStop {
+        number: String,
+        default: Vec<Element>,
+        choices: Vec<String>,
+        transform: Option<Transform>,
+    },
+    /// A variable such as `$CURRENT_YEAR` or `${TM_FILENAME:default}`.
+    Variable {
+        name: String,
+        default: Option<Vec<Element>>,
+        transform: Option<Transform>,
+    },
+}
+
+/// Parses a body written in the TextMate syntax used by VS Code snippets, such as `$1`,
+/// `${1:default}`, `${1|one,two|}` or `$CURRENT_YEAR`, into sections. Tab-stops nested
+/// in a default only keep their text, and variables about the open editor, such as
+/// `$TM_FILENAME`, become placeholders named after them as VS Code does for unknown ones.
+pub fn parse(body: &str) -> Vec<Section> {
+    let chars = body
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .collect::<Vec<char>>();
+    let (elements, _) = elements(&chars, false);
+
+    let mut sections = Vec::new();
+    let mut static_txt = Vec::new();
+    for element in elements {
+        match element {
+            Element::Text(c) => static_txt.push(c),
+            Element::TabStop {
+                number,
+                default,
+                choices,
+                transform,
+            } => {
+                let section = Section::named(std::mem::take(&mut static_txt), &number)
+                    .with_transform(transform);
+                sections.push(match choices.is_empty() {
+                    true => section.with_default(&text(&default)),
+                    false => section.with_choices(choices),
+                });
+            }
+            Element::Variable {
+                name,
+                default,
+                transform,
+            } => match Variable::parse_textmate(&name) {
+                Some(variable) => {
+                    let value = Some(variable.resolve())
+                        .filter(|value| !value.is_empty())
+                        .or_else(|| default.as_ref().map(|d| text(d)))
+                        .unwrap_or_default();
+                    let value = match transform {
+                        Some(transform) => transform.apply(&value),
+                        None => value,
+                    };
+                    static_txt.extend(value.chars());
+                }
+                None => {
+                    let default = default.as_ref().map_or(name.clone(), |d| text(d));
+                    let section = Section::named(std::mem::take(&mut static_txt), &name)
+                        .with_default(&default)
+                        .with_transform(transform);
+                    sections.push(section);
+                }
+            },
+        }
+    }
+
+    sections.push(Section::tail(static_txt));
+    sections
+}
+
+/// Reads text and placeholders, stopping at an unescaped `}` when nested in a default.
+/// Returns them along with the number of characters read.
+fn elements(chars: &[char], nested: bool) -> (Vec<Element>, usize) {
+    let mut elements = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match &chars[i..] {
+            ['\\', c @ ('$' | '}' | '\\'), ..] => {
+                elements.push(Element::Text(*c));
+                i += 2;
+            }
+            ['}', ..] if nested => break,
+            rest @ ['$', ..] => match element(rest) {
+                Some((element, len)) => {
+                    elements.push(element);
+                    i += len;
+                }
+                None => {
+                    elements.push(Element::Text('$'));
+                    i += 1;
+                }
+            },
+            rest => {
+                elements.push(Element::Text(rest[0]));
+                i += 1;
+            }
+        }
+    }
+
+    (elements, i)
+}
+
+/// Reads a tab-stop or variable starting with `$`, returning it along with its length.
+fn element(chars: &[char]) -> Option<(Element, usize)> {
+    let rest = chars.strip_prefix(&['$'])?;
+    let Some(rest) = rest.strip_prefix(&['{']) else {
+        let (name, len) = identifier(rest)?;
+        return Some((placeholder(name, None, Vec::new(), None), len + 1));
+    };
+
+    let (name, mut i) = identifier(rest)?;
+    let number = name.chars().all(|c| c.is_ascii_digit());
+    let element = match rest.get(i)? {
+        '}' => placeholder(name, None, Vec::new(), None),
+        ':' => {
+            let (default, len) = elements(&rest[i + 1..], true);
+            i += len + 1;
+            placeholder(name, Some(default), Vec::new(), None)
+        }
+        '|' if number => {
+            let (choices, len) = choices(&rest[i + 1..])?;
+            i += len + 1;
+            placeholder(name, None, choices, None)
+        }
+        '/' => {
+            let (transform, len) = transform(&rest[i + 1..])?;
+            i += len + 1;
+            placeholder(name, None, Vec::new(), transform)
+        }
+        _ => return None,
+    };
+
+    // Two for the leading `${` and one for the closing `}`
+    (rest.get(i) == Some(&'}')).then_some((element, i + 3))
+}
+
+fn placeholder(
+    name: String,
+    default: Option<Vec<Element>>,
+    choices: Vec<String>,
+    transform: Option<Transform>,
+) -> Element {
+    match name.chars().all(|c| c.is_ascii_digit()) {
+        true => Element::TabStop {
+            number: name,
+            default: default.unwrap_or_default(),
+            choices,
+            transform,
+        },
+        false => Element::Variable {
+            name,
+            default,
+            transform,
+        },
+    }
+}
+
+/// Reads the number of a tab-stop or the name of a variable, along with its length.
+fn identifier(chars: &[char]) -> Option<(String, usize)> {
+    let len = match chars.first()? {
+        c if c.is_ascii_digit() => chars.iter().take_while(|c| c.is_ascii_digit()).count(),
+        c if c.is_ascii_alphabetic() || *c == '_' => chars
+            .iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+            .count(),
+        _ => return None,
+    };
+
+    Some((chars[..len].iter().collect(), len))
+}
+
+/// Reads the choices of `${1|one,two|}` up to the closing `|`, where `\,` and `\|` are
+/// written literally.
+fn choices(chars: &[char]) -> Option<(Vec<String>, usize)> {
+    let mut choices = vec![String::new()];
+    let mut i = 0;
+    loop {
+        match chars.get(i..)? {
+            ['\\', c @ (',' | '|' | '\\'), ..] => {
+                choices.last_mut()?.push(*c);
+                i += 2;
+            }
+            [',', ..] => {
+                choices.push(String::new());
+                i += 1;
+            }
+            ['|', ..] => break,
+            [c, ..] => {
+                choices.last_mut()?.push(*c);
+                i += 1;
+            }
+            [] => return None,
+        }
+    }
+
+    choices.retain(|choice| !choice.is_empty());
+    (!choices.is_empty()).then_some((choices, i + 1))
+}
+
+/// Reads a transform written as `regex/format/options` up to the closing `}`. Formats and
+/// options which can't be applied leave the value as it is.
+fn transform(chars: &[char]) -> Option<(Option<Transform>, usize)> {
+    let mut parts = vec![String::new()];
+    let mut i = 0;
+    loop {
+        match chars.get(i..)? {
+            ['\\', '/', ..] => {
+                parts.last_mut()?.push('/');
+                i += 2;
+            }
+            ['\\', c, ..] => {
+                parts.last_mut()?.extend(['\\', *c]);
+                i += 2;
+            }
+            ['/', ..] if parts.len() < 3 => {
+                parts.push(String::new());
+                i += 1;
+            }
+            ['}', ..] => break,
+            [c, ..] => {
+                parts.last_mut()?.push(*c);
+                i += 1;
+            }
+            [] => return None,
+        }
+    }
+
+    match parts.as_slice() {
+        [regex, format, options] => Some((Transform::replace(regex, format, options), i)),
+        _ => None,
+    }
+}
+
+/// The text a default shows, with nested tab-stops showing their own default.
+fn text(elements: &[Element]) -> String {
+    elements
+        .iter()
+        .map(|element| match element {
+            Element::Text(c) => c.to_string(),
+            Element::TabStop {
+                default, choices, ..
+            } => choices.first().cloned().unwrap_or_else(|| text(default)),
+            Element::Variable { name, default, .. } => Variable::parse_textmate(name)
+                .map(|variable| variable.resolve())
+                .filter(|value| !value.is_empty())
+                .or_else(|| default.as_ref().map(|d| text(d)))
+                .unwrap_or_else(|| name.clone()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::sections::{section::Section, transform::Transform};
+
+    #[test]
+    fn parses_tab_stops() {
+        assert_eq!(
+            parse("for $1 in ${2}:\n\t$0"),
+            vec![
+                named("for ", "1"),
+                named(" in ", "2"),
+                named(":\n\t", "0"),
+                tail(""),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_defaults() {
+        assert_eq!(
+            parse("let ${1:name}: ${2:int} = ${3:};"),
+            vec![
+                named("let ", "1").with_default("name"),
+                named(": ", "2").with_default("int"),
+                named(" = ", "3"),
+                tail(";"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_tab_stops_keep_their_text() {
+        assert_eq!(
+            parse("${1:pub ${2:fn}} x"),
+            vec![named("", "1").with_default("pub fn"), tail(" x")]
+        );
+    }
+
+    #[test]
+    fn parses_choices() {
+        assert_eq!(
+            parse("${1|GET,POST,a\\,b|}"),
+            vec![
+                named("", "1").with_choices(vec![
+                    "GET".to_owned(),
+                    "POST".to_owned(),
+                    "a,b".to_owned()
+                ]),
+                tail(""),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_transforms() {
+        let sections = parse("${1/-/_/g}");
+        assert_eq!(sections[0].transform, Transform::replace("-", "_", "g"));
+    }
+
+    #[test]
+    fn braces_and_escapes_are_literal() {
+        assert_eq!(
+            parse("fn main() {\n\t\\$1 \\} \\\\ $ {}\n}"),
+            vec![tail("fn main() {\n\t$1 } \\ $ {}\n}")]
+        );
+    }
+
+    #[test]
+    fn resolves_known_variables() {
+        let sections = parse("$UUID ${CURRENT_YEAR/^..//}");
+        assert_eq!(sections.len(), 1);
+        let text = sections[0].text();
+        assert_eq!(text.len(), 36 + 1 + 2);
+    }
+
+    #[test]
+    fn unknown_variables_become_placeholders() {
+        assert_eq!(
+            parse("// $TM_FILENAME ${TM_SELECTED_TEXT:text}"),
+            vec![
+                named("// ", "TM_FILENAME").with_default("TM_FILENAME"),
+                named(" ", "TM_SELECTED_TEXT").with_default("text"),
+                tail(""),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_literal() {
+        assert_eq!(parse("${1:a"), vec![tail("${1:a")]);
+        assert_eq!(parse("${1|a,b}"), vec![tail("${1|a,b}")]);
+    }
+
+    fn named(prefix: &str, name: &str) -> Section {
+        Section::named(prefix.chars().collect(), name)
+    }
+
+    fn tail(prefix: &str) -> Section {
+        Section::tail(prefix.chars().collect())
+    }
+}
@@ -0,0 +1,236 @@
+use regex::{Regex, RegexBuilder};
+
+/// Conversion applied to the value of a mirrored placeholder, such as `{name/snake}`
+/// or `{name/regex/replacement/flags}`.
+#[derive(Debug, Clone)]
+pub enum Transform {
+    Upper,
+    Lower,
+    Snake,
+    Kebab,
+    Camel,
+    Pascal,
+    Replace {
+        regex: Regex,
+        replacement: String,
+        global: bool,
+    },
+}
+
+impl PartialEq for Transform {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Transform::Replace {
+                    regex,
+                    replacement,
+                    global,
+                },
+                Transform::Replace {
+                    regex: other_regex,
+                    replacement: other_replacement,
+                    global: other_global,
+                },
+            ) => {
+                regex.as_str() == other_regex.as_str()
+                    && replacement == other_replacement
+                    && global == other_global
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Transform {
+    /// Parses everything written after the first `/`. A replacement is written as
+    /// `regex/replacement/flags` where `/` can be escaped with `\/`, the flags are `g`
+    /// to replace every match and `i` to ignore case.
+    pub fn parse(content: &[char]) -> Option<Self> {
+        let parts = Transform::split(content);
+        let transform = match parts.as_slice() {
+            [case] => match case.as_str() {
+                "upper" => Transform::Upper,
+                "lower" => Transform::Lower,
+                "snake" => Transform::Snake,
+                "kebab" => Transform::Kebab,
+                "camel" => Transform::Camel,
+                "pascal" => Transform::Pascal,
+                _ => return None,
+            },
+            [regex, replacement, flags] => Transform::replace(regex, replacement, flags)?,
+            _ => return None,
+        };
+
+        Some(transform)
+    }
+
+    /// A regex replacement where the flags are `g` to replace every match and `i` to
+    /// ignore case.
+    pub fn replace(regex: &str, replacement: &str, flags: &str) -> Option<Self> {
+        if flags.chars().any(|c| c != 'g' && c != 'i') {
+            return None;
+        }
+
+        let regex = RegexBuilder::new(regex)
+            .case_insensitive(flags.contains('i'))
+            .build()
+            .ok()?;
+        Some(Transform::Replace {
+            regex,
+            replacement: replacement.to_owned(),
+            global: flags.contains('g'),
+        })
+    }
+
+    fn split(content: &[char]) -> Vec<String> {
+        let mut parts = vec![String::new()];
+        let mut chars = content.iter().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&&'/') => {
+                    parts.last_mut().unwrap().push('/');
+                    chars.next();
+                }
+                '/' => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(*c),
+            }
+        }
+        parts
+    }
+
+    pub fn apply(&self, value: &str) -> String {
+        let words = || words(value);
+        match self {
+            Transform::Upper => value.to_uppercase(),
+            Transform::Lower => value.to_lowercase(),
+            Transform::Snake => words().join("_"),
+            Transform::Kebab => words().join("-"),
+            Transform::Camel => words()
+                .iter()
+                .enumerate()
+                .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+                .collect(),
+            Transform::Pascal => words().iter().map(|w| capitalize(w)).collect(),
+            Transform::Replace {
+                regex,
+                replacement,
+                global,
+            } => match global {
+                true => regex.replace_all(value, replacement.as_str()).into_owned(),
+                false => regex.replace(value, replacement.as_str()).into_owned(),
+            },
+        }
+    }
+}
+
+/// Splits a value into lowercase words on separators and case changes,
+/// `HTTPServer_config` becomes `http`, `server` and `config`.
+fn words(value: &str) -> Vec<String> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && previous.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_numeric()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Transform;
+
+    #[test]
+    fn converts_case() {
+        assert_eq!(parse("upper").apply("my type"), "MY TYPE");
+        assert_eq!(parse("lower").apply("MyType"), "mytype");
+        assert_eq!(parse("snake").apply("MyType"), "my_type");
+        assert_eq!(parse("kebab").apply("MyType"), "my-type");
+        assert_eq!(parse("camel").apply("my_type"), "myType");
+        assert_eq!(parse("pascal").apply("my-type"), "MyType");
+    }
+
+    #[test]
+    fn splits_acronyms_and_numbers() {
+        assert_eq!(
+            parse("snake").apply("HTTPServer2Config"),
+            "http_server2_config"
+        );
+        assert_eq!(parse("kebab").apply("  some  spaced  "), "some-spaced");
+        assert_eq!(parse("snake").apply(""), "");
+    }
+
+    #[test]
+    fn replaces_first_match() {
+        assert_eq!(parse("a/b/").apply("aaa"), "baa");
+    }
+
+    #[test]
+    fn replaces_globally() {
+        assert_eq!(parse("a/b/g").apply("aaa"), "bbb");
+    }
+
+    #[test]
+    fn replaces_ignoring_case() {
+        assert_eq!(parse("a/b/gi").apply("AaA"), "bbb");
+    }
+
+    #[test]
+    fn replaces_with_groups() {
+        assert_eq!(
+            parse("(\\w+)@(\\w+)/$2 at $1/").apply("me@host"),
+            "host at me"
+        );
+    }
+
+    #[test]
+    fn escapes_slashes() {
+        assert_eq!(parse("\\//-/g").apply("a/b/c"), "a-b-c");
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert!(Transform::parse(&chars("title")).is_none());
+        assert!(Transform::parse(&chars("a/b")).is_none());
+        assert!(Transform::parse(&chars("a/b/x")).is_none());
+        assert!(Transform::parse(&chars("(/b/")).is_none());
+    }
+
+    fn parse(content: &str) -> Transform {
+        Transform::parse(&chars(content)).unwrap()
+    }
+
+    fn chars(content: &str) -> Vec<char> {
+        content.chars().collect()
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::NaiveDate;
+use regex::Regex;
+use std::path::Path;
+
+/// Type declared on a placeholder such as `{count!int}`, restricting what can be typed
+/// and which values are accepted before the snippet is finished.
+#[derive(Debug, Clone)]
+pub enum Validation {
+    Int,
+    Float,
+    Date,
+    File,
+    Regex(Regex),
+}
+
+impl PartialEq for Validation {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Validation::Regex(regex), Validation::Regex(other)) => {
+                regex.as_str() == other.as_str()
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Validation {
+    pub fn parse(kind: &str) -> Option<Self> {
+        let validation = match kind {
+            "int" => Validation::Int,
+            "float" => Validation::Float,
+            "date" => Validation::Date,
+            "file" => Validation::File,
+            _ => Validation::Regex(Regex::new(kind.strip_prefix("regex=")?).ok()?),
+        };
+
+        Some(validation)
+    }
+
+    /// Whether the character could be part of a valid value, used to refuse typing it.
+    pub fn accepts(&self, c: char) -> bool {
+        match self {
+            Validation::Int => c.is_ascii_digit() || c == '-',
+            Validation::Float => c.is_ascii_digit() || matches!(c, '-' | '.' | 'e' | 'E'),
+            Validation::Date => c.is_ascii_digit() || c == '-',
+            Validation::File | Validation::Regex(_) => true,
+        }
+    }
+
+    /// Checks a value, returning the message to show when it is not accepted.
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let valid = match self {
+            Validation::Int => value.parse::<i64>().is_ok(),
+            Validation::Float => value.parse::<f64>().is_ok(),
+            Validation::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            Validation::File => !value.is_empty() && Path::new(value).exists(),
+            Validation::Regex(regex) => regex.is_match(value),
+        };
+
+        match valid {
+            true => Ok(()),
+            false => Err(match self {
+                Validation::Int => "Expected a whole number".to_owned(),
+                Validation::Float => "Expected a number".to_owned(),
+                Validation::Date => "Expected a date such as 2024-12-31".to_owned(),
+                Validation::File => format!("{value:?} does not exist"),
+                Validation::Regex(regex) => format!("Expected a value matching {}", regex.as_str()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Validation;
+
+    #[test]
+    fn parses_types() {
+        assert_eq!(Validation::parse("int"), Some(Validation::Int));
+        assert_eq!(Validation::parse("date"), Some(Validation::Date));
+        assert!(Validation::parse("regex=^a+$").is_some());
+        assert_eq!(Validation::parse("regex=("), None);
+        assert_eq!(Validation::parse("8080"), None);
+    }
+
+    #[test]
+    fn validates_int() {
+        assert!(Validation::Int.validate("-42").is_ok());
+        assert!(Validation::Int.validate("4.2").is_err());
+        assert!(Validation::Int.validate("").is_err());
+        assert!(Validation::Int.accepts('7'));
+        assert!(!Validation::Int.accepts('a'));
+    }
+
+    #[test]
+    fn validates_float() {
+        assert!(Validation::Float.validate("4.2").is_ok());
+        assert!(Validation::Float.validate("1e3").is_ok());
+        assert!(Validation::Float.validate("4.2.1").is_err());
+    }
+
+    #[test]
+    fn validates_date() {
+        assert!(Validation::Date.validate("2024-02-29").is_ok());
+        assert!(Validation::Date.validate("2023-02-29").is_err());
+        assert!(!Validation::Date.accepts('/'));
+    }
+
+    #[test]
+    fn validates_file() {
+        assert!(Validation::File.validate("Cargo.toml").is_ok());
+        assert!(Validation::File.validate("missing.file").is_err());
+        assert!(Validation::File.validate("").is_err());
+    }
+
+    #[test]
+    fn validates_regex() {
+        let email = Validation::parse("regex=^.+@.+$").unwrap();
+        assert!(email.validate("me@example.com").is_ok());
+        assert_eq!(
+            email.validate("me"),
+            Err("Expected a value matching ^.+@.+$".to_owned())
+        );
+    }
+}
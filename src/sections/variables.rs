@@ -0,0 +1,170 @@
+use arboard::Clipboard;
+use chrono::Local;
+use std::env;
+
+/// Built-in values resolved when a snippet is opened, written as `{$DATE}` or used as
+/// the default of an editable placeholder such as `{date:$DATE}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Variable {
+    Date,
+    Time,
+    Uuid,
+    User,
+    Hostname,
+    Cwd,
+    Clipboard,
+    Env(String),
+    /// The current date and time in a chrono format, such as `%Y` for `$CURRENT_YEAR`.
+    Now(&'static str),
+    Random,
+    RandomHex,
+    WorkspaceName,
+}
+
+impl Variable {
+    /// Parses a variable name written without its leading `$`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let variable = match name {
+            "DATE" => Variable::Date,
+            "TIME" => Variable::Time,
+            "UUID" => Variable::Uuid,
+            "USER" => Variable::User,
+            "HOSTNAME" => Variable::Hostname,
+            "CWD" => Variable::Cwd,
+            "CLIPBOARD" => Variable::Clipboard,
+            _ => match name.strip_prefix("ENV:") {
+                Some(env) if !env.is_empty() => Variable::Env(env.to_owned()),
+                _ => return None,
+            },
+        };
+
+        Some(variable)
+    }
+
+    /// Parses a variable of the TextMate syntax used by VS Code snippets such as
+    /// `$CURRENT_YEAR`. Variables about the open editor, such as `$TM_FILENAME`, have no
+    /// value in a terminal and are not known.
+    pub fn parse_textmate(name: &str) -> Option<Self> {
+        let variable = match name {
+            "CLIPBOARD" => Variable::Clipboard,
+            "UUID" => Variable::Uuid,
+            "RANDOM" => Variable::Random,
+            "RANDOM_HEX" => Variable::RandomHex,
+            "WORKSPACE_FOLDER" => Variable::Cwd,
+            "WORKSPACE_NAME" => Variable::WorkspaceName,
+            "CURRENT_YEAR" => Variable::Now("%Y"),
+            "CURRENT_YEAR_SHORT" => Variable::Now("%y"),
+            "CURRENT_MONTH" => Variable::Now("%m"),
+            "CURRENT_MONTH_NAME" => Variable::Now("%B"),
+            "CURRENT_MONTH_NAME_SHORT" => Variable::Now("%b"),
+            "CURRENT_DATE" => Variable::Now("%d"),
+            "CURRENT_DAY_NAME" => Variable::Now("%A"),
+            "CURRENT_DAY_NAME_SHORT" => Variable::Now("%a"),
+            "CURRENT_HOUR" => Variable::Now("%H"),
+            "CURRENT_MINUTE" => Variable::Now("%M"),
+            "CURRENT_SECOND" => Variable::Now("%S"),
+            "CURRENT_SECONDS_UNIX" => Variable::Now("%s"),
+            "CURRENT_TIMEZONE_OFFSET" => Variable::Now("%:z"),
+            _ => return None,
+        };
+
+        Some(variable)
+    }
+
+    /// Resolves the current value, values which can not be retrieved are left empty.
+    pub fn resolve(&self) -> String {
+        match self {
+            Variable::Date => Local::now().format("%Y-%m-%d").to_string(),
+            Variable::Time => Local::now().format("%H:%M:%S").to_string(),
+            Variable::Uuid => uuid::Uuid::new_v4().to_string(),
+            Variable::User => env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_default(),
+            Variable::Hostname => gethostname::gethostname().to_string_lossy().into_owned(),
+            Variable::Cwd => env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            Variable::Clipboard => Clipboard::new()
+                .and_then(|mut clipboard| clipboard.get_text())
+                .unwrap_or_default(),
+            Variable::Env(name) => env::var(name).unwrap_or_default(),
+            Variable::Now(format) => Local::now().format(format).to_string(),
+            Variable::Random => format!("{:06}", uuid::Uuid::new_v4().as_u128() % 1_000_000),
+            Variable::RandomHex => format!("{:06x}", uuid::Uuid::new_v4().as_u128() & 0xffffff),
+            Variable::WorkspaceName => env::current_dir()
+                .ok()
+                .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Variable;
+
+    #[test]
+    fn parses_known_variables() {
+        assert_eq!(Variable::parse("DATE"), Some(Variable::Date));
+        assert_eq!(Variable::parse("HOSTNAME"), Some(Variable::Hostname));
+        assert_eq!(
+            Variable::parse("ENV:HOME"),
+            Some(Variable::Env("HOME".to_owned()))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        assert_eq!(Variable::parse("date"), None);
+        assert_eq!(Variable::parse("ENV:"), None);
+        assert_eq!(Variable::parse("PATH"), None);
+    }
+
+    #[test]
+    fn parses_textmate_variables() {
+        assert_eq!(
+            Variable::parse_textmate("CURRENT_YEAR"),
+            Some(Variable::Now("%Y"))
+        );
+        assert_eq!(Variable::parse_textmate("UUID"), Some(Variable::Uuid));
+        assert_eq!(Variable::parse_textmate("TM_FILENAME"), None);
+        assert_eq!(Variable::parse_textmate("DATE"), None);
+    }
+
+    #[test]
+    fn resolves_random_values() {
+        let random = Variable::Random.resolve();
+        assert_eq!(random.len(), 6);
+        assert!(random.chars().all(|c| c.is_ascii_digit()));
+        let hex = Variable::RandomHex.resolve();
+        assert_eq!(hex.len(), 6);
+        assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn resolves_date_and_time() {
+        let date = Variable::Date.resolve();
+        assert_eq!(date.len(), 10);
+        assert_eq!(date.matches('-').count(), 2);
+        assert_eq!(Variable::Time.resolve().matches(':').count(), 2);
+    }
+
+    #[test]
+    fn resolves_unique_uuids() {
+        assert_eq!(Variable::Uuid.resolve().len(), 36);
+        assert_ne!(Variable::Uuid.resolve(), Variable::Uuid.resolve());
+    }
+
+    #[test]
+    fn resolves_environment() {
+        let path = std::env::var("PATH").unwrap_or_default();
+        assert_eq!(Variable::Env("PATH".to_owned()).resolve(), path);
+        assert_eq!(Variable::Env("SNIPPSET_MISSING".to_owned()).resolve(), "");
+    }
+
+    #[test]
+    fn resolves_current_directory() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(Variable::Cwd.resolve(), cwd.display().to_string());
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A snippet along with the metadata used to organise and find it. Snippets without
+/// metadata are written as a plain string.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "Entry", into = "Entry")]
+pub struct Snippet {
+    pub body: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Language of the snippet such as `sql` or `bash`.
+    pub language: Option<String>,
+    /// Other names the snippet can be found by.
+    pub aliases: Vec<String>,
+}
+
+/// How a snippet is written in a file, either `"body"` or `{ "body": "...", "tags": [...] }`.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum Entry {
+    Body(String),
+    Full {
+        body: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        aliases: Vec<String>,
+    },
+}
+
+impl From<Entry> for Snippet {
+    fn from(entry: Entry) -> Self {
+        match entry {
+            Entry::Body(body) => Snippet::new(&body),
+            Entry::Full {
+                body,
+                description,
+                tags,
+                language,
+                aliases,
+            } => Snippet {
+                body,
+                description,
+                tags,
+                language,
+                aliases,
+            },
+        }
+    }
+}
+
+impl From<Snippet> for Entry {
+    fn from(snippet: Snippet) -> Self {
+        match snippet.has_metadata() {
+            false => Entry::Body(snippet.body),
+            true => Entry::Full {
+                body: snippet.body,
+                description: snippet.description,
+                tags: snippet.tags,
+                language: snippet.language,
+                aliases: snippet.aliases,
+            },
+        }
+    }
+}
+
+impl Snippet {
+    pub fn new(body: &str) -> Self {
+        Snippet {
+            body: body.to_owned(),
+            ..Snippet::default()
+        }
+    }
+
+    /// Whether every word of the query is in the title, body or metadata, ignoring case.
+    pub fn matches(&self, title: &str, query: &str) -> bool {
+        let text = [title, &self.body]
+            .into_iter()
+            .chain(self.description.as_deref())
+            .chain(self.language.as_deref())
+            .chain(self.tags.iter().map(String::as_str))
+            .chain(self.aliases.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .to_lowercase();
+        query
+            .split_whitespace()
+            .all(|word| text.contains(&word.to_lowercase()))
+    }
+
+    pub fn has_metadata(&self) -> bool {
+        self.description.is_some()
+            || !self.tags.is_empty()
+            || self.language.is_some()
+            || !self.aliases.is_empty()
+    }
+}
+
+/// Narrows the snippets listed to the ones with every tag and the language given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub tags: Vec<String>,
+    pub language: Option<String>,
+}
+
+impl Filter {
+    pub fn keeps(&self, snippet: &Snippet) -> bool {
+        let tagged = self
+            .tags
+            .iter()
+            .all(|tag| snippet.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        let language = self.language.as_ref().is_none_or(|language| {
+            snippet
+                .language
+                .as_ref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(language))
+        });
+
+        tagged && language
+    }
+}
+
+/// A snippet as listed in the picker, its metadata is shown so typing any of it finds
+/// the snippet. The source is the layer it comes from when there are several.
+pub struct Listed<'a> {
+    pub title: &'a String,
+    pub snippet: &'a Snippet,
+    pub source: Option<&'a str>,
+}
+
+impl fmt::Display for Listed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title)?;
+        if let Some(language) = &self.snippet.language {
+            write!(f, " [{language}]")?;
+        }
+        if let Some(description) = &self.snippet.description {
+            write!(f, " - {description}")?;
+        }
+        for tag in &self.snippet.tags {
+            write!(f, " #{tag}")?;
+        }
+        if !self.snippet.aliases.is_empty() {
+            write!(f, " ({})", self.snippet.aliases.join(", "))?;
+        }
+        if let Some(source) = self.source {
+            write!(f, " @ {source}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Filter, Listed, Snippet};
+
+    #[test]
+    fn reads_plain_and_full_entries() {
+        let snippets: Vec<Snippet> = serde_json::from_str(
+            r#"["SELECT 1", { "body": "SELECT {x}", "tags": ["db"], "language": "sql" }]"#,
+        )
+        .unwrap();
+        assert_eq!(snippets[0], Snippet::new("SELECT 1"));
+        assert_eq!(snippets[1].body, "SELECT {x}");
+        assert_eq!(snippets[1].tags, vec!["db"]);
+        assert_eq!(snippets[1].language, Some("sql".to_owned()));
+        assert_eq!(snippets[1].description, None);
+    }
+
+    #[test]
+    fn writes_plain_string_without_metadata() {
+        let snippet = Snippet::new("ls {path}");
+        assert_eq!(serde_json::to_string(&snippet).unwrap(), r#""ls {path}""#);
+
+        let snippet = Snippet {
+            aliases: vec!["list".to_owned()],
+            ..snippet
+        };
+        assert_eq!(
+            serde_json::to_string(&snippet).unwrap(),
+            r#"{"body":"ls {path}","aliases":["list"]}"#
+        );
+    }
+
+    #[test]
+    fn filters_by_tags_and_language() {
+        let snippet = Snippet {
+            tags: vec!["db".to_owned(), "Postgres".to_owned()],
+            language: Some("SQL".to_owned()),
+            ..Snippet::new("")
+        };
+        assert!(Filter::default().keeps(&snippet));
+        assert!(filter(&["postgres", "db"], Some("sql")).keeps(&snippet));
+        assert!(!filter(&["db", "mysql"], None).keeps(&snippet));
+        assert!(!filter(&[], Some("bash")).keeps(&snippet));
+        assert!(!filter(&["db"], None).keeps(&Snippet::new("")));
+    }
+
+    #[test]
+    fn matches_every_word() {
+        let snippet = Snippet {
+            description: Some("Count rows".to_owned()),
+            tags: vec!["Postgres".to_owned()],
+            ..Snippet::new("SELECT count(*) FROM {table}")
+        };
+        assert!(snippet.matches("count", "postgres ROWS"));
+        assert!(snippet.matches("count", "from table"));
+        assert!(!snippet.matches("count", "rows mysql"));
+        assert!(snippet.matches("count", ""));
+    }
+
+    #[test]
+    fn lists_metadata() {
+        let snippet = Snippet {
+            description: Some("Count rows".to_owned()),
+            tags: vec!["db".to_owned()],
+            language: Some("sql".to_owned()),
+            aliases: vec!["rows".to_owned(), "total".to_owned()],
+            ..Snippet::new("")
+        };
+        let title = "count".to_owned();
+        let listed = Listed {
+            title: &title,
+            snippet: &snippet,
+            source: Some("/etc/snippset"),
+        };
+        assert_eq!(
+            listed.to_string(),
+            "count [sql] - Count rows #db (rows, total) @ /etc/snippset"
+        );
+    }
+
+    fn filter(tags: &[&str], language: Option<&str>) -> Filter {
+        Filter {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            language: language.map(str::to_owned),
+        }
+    }
+}
@@ -1,30 +1,77 @@
-use crate::sections::section_manager::SectionManager;
-use crossterm::event::{read, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crate::keys::Keys;
+use crate::sections::{
+    choice::Choice, parse_options::ParseOptions, section::Section, section_manager::SectionManager,
+};
+use crate::theme::Theme;
+use anyhow::{bail, Result};
+use crossterm::{
+    event::{read, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
 use ratatui::{
+    backend::CrosstermBackend,
     buffer::Buffer,
-    layout::Rect,
-    style::{Color, Stylize},
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
     symbols::border,
     text::{Line, Span},
     widgets::{Block, Paragraph, Widget},
-    Frame,
+    Frame, Terminal,
 };
-use anyhow::{bail, Result};
+use std::io::stderr;
+
+/// The editor's screen, drawn on stderr so stdout only gets the snippet. The terminal is
+/// restored once dropped, even when panicking.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Screen> {
+        enable_raw_mode()?;
+        execute!(stderr(), EnterAlternateScreen)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(stderr(), LeaveAlternateScreen);
+    }
+}
 
 pub struct SnippetEngine {
     title: String,
     manager: SectionManager,
+    theme: Theme,
+    keys: Keys,
 }
 
 impl Widget for &SnippetEngine {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let title = Line::from(format!(" {} ", self.title).bold());
-        let instructions = Line::from(vec![
+        let keys = &self.keys;
+        let key = |text: String| text.fg(self.theme.keys).bold();
+        let mut instructions = vec![
             " Next Snipp ".into(),
-            "<Enter>".blue().bold(),
+            key(format!("<{}>", keys.next)),
             " Previous Snipp ".into(),
-            "<Esc> ".blue().bold(),
-        ]);
+            key(format!("<{}> ", keys.previous)),
+        ];
+
+        let active = self.manager.sections.get(self.manager.active_index);
+        if active.is_some_and(|s| s.choice.is_some()) {
+            instructions.extend(["Choose ".into(), key("<Up/Down> ".to_owned())]);
+        }
+        if let Some(row) = active.and_then(|s| s.row.as_ref()) {
+            instructions.extend([
+                format!("Add {} ", row.name).into(),
+                key(format!("<{}>", keys.add_row)),
+                format!(" Remove {} ", row.name).into(),
+                key(format!("<{}> ", keys.remove_row)),
+            ]);
+        }
+        let instructions = Line::from(instructions);
 
         let block = Block::bordered()
             .title(title.centered())
@@ -38,36 +85,42 @@ impl Widget for &SnippetEngine {
             .iter()
             .enumerate()
             .flat_map(|(i, s)| {
-                let mut suffix: Vec<Span> = match s.suffix.as_ref() {
-                    Some(editable) => std::iter::once('[')
-                        .chain(editable.chars())
-                        .chain(std::iter::once(']'))
-                        .map(|s| s.to_string().bold().yellow().underlined())
-                        .collect(),
-                    None => vec![],
+                let suffix = match s.choice.as_ref() {
+                    Some(choice) => self.choice_spans(i, choice),
+                    None => self.editable_spans(i, s),
                 };
 
-                if self.manager.active_index == i && s.suffix.is_some() {
-                    match s
-                        .suffix
-                        .as_ref()
-                        // +1 since we add extra characters to our string
-                        .and_then(|e| suffix.get_mut(e.insertion_position() + 1))
-                    {
-                        Some(s) => s.style.bg = Some(Color::White),
-                        None => suffix.last_mut().unwrap().style.bg = Some(Color::White),
-                    }
-                }
-
+                // Optional groups which will be left out are dimmed
+                let dropped = self.manager.is_dropped(s);
+                let separator = self.manager.separator(i).unwrap_or_default();
                 Line::from_iter(
-                    s.prefix
-                        .iter()
-                        .map(|c| Span::from(c.to_string()))
-                        .chain(suffix),
+                    separator.chars().map(|c| Span::from(c.to_string())).chain(
+                        s.prefix
+                            .iter()
+                            .map(|c| Span::from(c.to_string()))
+                            .chain(suffix)
+                            .map(|span| match dropped {
+                                true => span.dim(),
+                                false => span,
+                            }),
+                    ),
                 )
             })
             .collect();
 
+        // Problems with the active placeholder, such as a failed command or a value not
+        // matching its type, are shown in a line below the snippet
+        let error = active.and_then(|s| s.problem());
+        let area = match error {
+            Some(error) => {
+                let [main, status] =
+                    Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+                Line::from(format!(" {error}").fg(self.theme.invalid).bold()).render(status, buf);
+                main
+            }
+            None => area,
+        };
+
         Paragraph::new(Line::from(text))
             .left_aligned()
             .wrap(ratatui::widgets::Wrap { trim: true })
@@ -77,15 +130,102 @@ impl Widget for &SnippetEngine {
 }
 
 impl SnippetEngine {
+    fn editable_spans(&self, index: usize, section: &Section) -> Vec<Span<'static>> {
+        // The active placeholder is edited as typed, while mirrors show their transform
+        let value = match self.manager.active_index == index {
+            true => section.suffix.as_ref().map(|e| e.chars()),
+            false => section
+                .suffix
+                .as_ref()
+                .map(|_| section.value().chars().collect()),
+        };
+
+        // Values which were typed but do not match their type are shown in red
+        let typed = value.as_ref().is_some_and(|v| !v.is_empty());
+        let color = match typed && section.validation_error().is_some() {
+            true => self.theme.invalid,
+            false => self.theme.placeholder,
+        };
+
+        let mut suffix: Vec<Span> = match value {
+            Some(value) => std::iter::once('[')
+                .chain(value)
+                .chain(std::iter::once(']'))
+                .map(|s| s.to_string().bold().fg(color).underlined())
+                .collect(),
+            None => vec![],
+        };
+
+        // Untouched defaults are shown dimmed, and fully highlighted when active
+        // since typing will replace them.
+        if section.suffix.as_ref().is_some_and(|e| e.is_selected()) {
+            let default_len = suffix.len() - 2;
+            for span in suffix.iter_mut().skip(1).take(default_len) {
+                span.style = span.style.italic().dim();
+                if self.manager.active_index == index {
+                    span.style.bg = Some(self.theme.selection);
+                }
+            }
+        }
+
+        if self.manager.active_index == index && section.suffix.is_some() {
+            match section
+                .suffix
+                .as_ref()
+                // +1 since we add extra characters to our string
+                .and_then(|e| suffix.get_mut(e.insertion_position() + 1))
+            {
+                Some(s) => s.style.bg = Some(self.theme.cursor),
+                None => suffix.last_mut().unwrap().style.bg = Some(self.theme.cursor),
+            }
+        }
+
+        suffix
+    }
+
+    fn choice_spans(&self, index: usize, choice: &Choice) -> Vec<Span<'static>> {
+        let value = choice.value().to_owned();
+        let color = self.theme.placeholder;
+        if self.manager.active_index != index {
+            return vec![format!("[{value}]").bold().fg(color).underlined()];
+        }
+
+        vec![
+            "[◂".bold().fg(color),
+            value.bold().fg(color).underlined().bg(self.theme.selection),
+            "▸]".bold().fg(color),
+        ]
+    }
+
     pub fn new(title: &str, snippet: &str) -> Self {
         Self {
             title: title.to_owned(),
             manager: SectionManager::new(snippet),
+            theme: Theme::default(),
+            keys: Keys::default(),
         }
     }
 
+    pub fn with_options(title: &str, snippet: &str, options: &ParseOptions) -> Self {
+        Self {
+            title: title.to_owned(),
+            manager: SectionManager::with_options(snippet, options),
+            theme: Theme::default(),
+            keys: Keys::default(),
+        }
+    }
+
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    pub fn with_keys(self, keys: Keys) -> Self {
+        Self { keys, ..self }
+    }
+
     pub fn start(&mut self) -> Result<String> {
-        let mut terminal = ratatui::init();
+        let screen = Screen::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stderr()))?;
         loop {
             terminal.draw(|frame| self.draw(frame))?;
             if let Event::Key(event) = read()? {
@@ -93,37 +233,85 @@ impl SnippetEngine {
                     continue;
                 }
 
-                if event.modifiers == KeyModifiers::CONTROL && event.code == KeyCode::Char('c') {
+                if self.keys.quit.matches(&event) {
                     break;
                 };
 
-                if self.handle_input(event.code).is_err() {
+                if self.handle_input(event).is_err() {
                     break;
                 };
             }
         }
 
-        ratatui::restore();
+        drop(screen);
+        if self.manager.first_invalid().is_some() {
+            bail!("Some placeholders do not have a valid value, nothing was copied");
+        }
+
         Ok(self.manager.text())
     }
 
-    fn handle_input(&mut self, keycode: KeyCode) -> Result<()> {
-        let editor = match self.manager.active_editable() {
-            Some(ed) => ed,
-            None => bail!("Couldn't retrieve editable section"),
+    fn handle_input(&mut self, event: impl Into<KeyEvent>) -> Result<()> {
+        let event = event.into();
+        // Rows of the repeat block the active placeholder belongs to
+        if self.keys.add_row.matches(&event) {
+            self.manager.add_row().unwrap_or_default();
+            return Ok(());
+        }
+        if self.keys.remove_row.matches(&event) {
+            self.manager.remove_row().unwrap_or_default();
+            return Ok(());
+        }
+
+        let section = match self.manager.active_section() {
+            Some(s) if s.suffix.is_some() => s,
+            _ => bail!("Couldn't retrieve editable section"),
         };
 
+        let keycode = event.code;
         match keycode {
+            _ if self.keys.previous.matches(&event) => {
+                self.manager.previous_section().unwrap_or_default()
+            }
+            _ if self.keys.next.matches(&event) => {
+                if self.manager.next_section().is_err() {
+                    // Finishing is only possible once every field is valid
+                    match self.manager.first_invalid() {
+                        Some(position) => self.manager.focus(position),
+                        None => bail!("There is no more sections"),
+                    }
+                }
+            }
+            _ if section.choice.is_some() => SnippetEngine::handle_choice(section, keycode),
+            _ => SnippetEngine::handle_editable(section, keycode),
+        }
+
+        self.manager.sync_mirrors();
+        Ok(())
+    }
+
+    fn handle_editable(section: &mut Section, keycode: KeyCode) {
+        let Some(editor) = section.suffix.as_mut() else {
+            return;
+        };
+
+        match keycode {
+            KeyCode::Char(c) if section.validation.as_ref().is_some_and(|v| !v.accepts(c)) => (),
             KeyCode::Char(c) => editor.insert(c),
             KeyCode::Left => editor.move_left(),
             KeyCode::Right => editor.move_right(),
             KeyCode::Backspace => editor.delete(),
-            KeyCode::Esc => self.manager.previous_section().unwrap_or_default(),
-            KeyCode::Enter => self.manager.next_section()?,
             _ => (),
         }
+    }
 
-        Ok(())
+    fn handle_choice(section: &mut Section, keycode: KeyCode) {
+        match keycode {
+            KeyCode::Char(c) => section.choose(|choice| choice.select_starting_with(c)),
+            KeyCode::Up | KeyCode::Left => section.choose(Choice::previous),
+            KeyCode::Down | KeyCode::Right => section.choose(Choice::next),
+            _ => (),
+        }
     }
 
     fn draw(&self, frame: &mut Frame) {
@@ -135,6 +323,7 @@ impl SnippetEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crossterm::event::KeyModifiers;
     use insta::assert_snapshot;
     use ratatui::{backend::TestBackend, Terminal};
 
@@ -147,4 +336,167 @@ mod tests {
             .unwrap();
         assert_snapshot!(terminal.backend());
     }
+
+    #[test]
+    fn test_render_default() {
+        let app = SnippetEngine::new("Server", "listen {port:8080}");
+        let mut terminal = Terminal::new(TestBackend::new(60, 6)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn test_render_choice() {
+        let app = SnippetEngine::new("Request", "curl -X {method|GET,POST} {url}");
+        let mut terminal = Terminal::new(TestBackend::new(60, 6)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn arrows_cycle_choices() {
+        let mut app = SnippetEngine::new("Log", "log.{level|info,warn,error}({})");
+        app.handle_input(KeyCode::Down).unwrap();
+        app.handle_input(KeyCode::Down).unwrap();
+        app.handle_input(KeyCode::Up).unwrap();
+        assert_eq!("log.warn()".to_owned(), app.manager.text());
+        app.handle_input(KeyCode::Char('e')).unwrap();
+        assert_eq!("log.error()".to_owned(), app.manager.text());
+    }
+
+    #[test]
+    fn test_render_choice_mirror() {
+        let mut app = SnippetEngine::new("Request", "{m|GET,POST} and {m}");
+        app.handle_input(KeyCode::Down).unwrap();
+        assert_eq!("POST and POST".to_owned(), app.manager.text());
+        let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn test_render_error() {
+        let app = SnippetEngine::new("Branch", "git checkout {$(git branch --show-current)}");
+        let mut terminal = Terminal::new(TestBackend::new(100, 6)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn test_render_transform() {
+        let mut app =
+            SnippetEngine::new("Module", "mod {name/snake};\npub use {name/snake}::{name};");
+        "HttpClient"
+            .chars()
+            .for_each(|c| app.handle_input(KeyCode::Char(c)).unwrap());
+        let mut terminal = Terminal::new(TestBackend::new(60, 7)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn test_render_invalid() {
+        let mut app = SnippetEngine::new("Mail", "mail {to!regex=^.+@.+$}");
+        "me".chars()
+            .for_each(|c| app.handle_input(KeyCode::Char(c)).unwrap());
+        let mut terminal = Terminal::new(TestBackend::new(60, 6)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn refuses_invalid_characters() {
+        let mut app = SnippetEngine::new("Count", "{count!int}");
+        "1a2"
+            .chars()
+            .for_each(|c| app.handle_input(KeyCode::Char(c)).unwrap());
+        assert_eq!("12".to_owned(), app.manager.text());
+    }
+
+    #[test]
+    fn finishing_moves_to_invalid_fields() {
+        let mut app = SnippetEngine::new("Retry", "retry {count!int} times in {}");
+        app.handle_input(KeyCode::Enter).unwrap();
+        assert_eq!(app.manager.active_index, 1);
+        app.handle_input(KeyCode::Enter).unwrap();
+        assert_eq!(app.manager.active_index, 0);
+        app.handle_input(KeyCode::Char('3')).unwrap();
+        app.handle_input(KeyCode::Enter).unwrap();
+        assert!(app.handle_input(KeyCode::Enter).is_err());
+    }
+
+    #[test]
+    fn test_render_repeat() {
+        let mut app = SnippetEngine::new(
+            "Struct",
+            "struct {name} {\n{*field:    {field}: {type},\n*}}",
+        );
+        "Point"
+            .chars()
+            .for_each(|c| app.handle_input(KeyCode::Char(c)).unwrap());
+        app.handle_input(KeyCode::Enter).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 6)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(&app, frame.area()))
+            .unwrap();
+        assert_snapshot!(terminal.backend());
+    }
+
+    #[test]
+    fn control_keys_add_and_remove_rows() {
+        let mut app = SnippetEngine::new("In", "IN ({*id:{id}*, })");
+        app.handle_input(KeyCode::Char('1')).unwrap();
+        app.handle_input(ctrl('n')).unwrap();
+        app.handle_input(KeyCode::Char('2')).unwrap();
+        app.handle_input(ctrl('n')).unwrap();
+        app.handle_input(KeyCode::Char('3')).unwrap();
+        assert_eq!("IN (1, 2, 3)".to_owned(), app.manager.text());
+
+        app.handle_input(KeyCode::Esc).unwrap();
+        app.handle_input(ctrl('d')).unwrap();
+        assert_eq!("IN (1, 3)".to_owned(), app.manager.text());
+    }
+
+    #[test]
+    fn typing_updates_mirrors() {
+        let mut app = SnippetEngine::new("Mirrors", "struct {name};\nimpl {name} {}");
+        "Point"
+            .chars()
+            .for_each(|c| app.handle_input(KeyCode::Char(c)).unwrap());
+        assert_eq!("struct Point;\nimpl Point ".to_owned(), app.manager.text());
+    }
+
+    #[test]
+    fn keys_can_be_rebound() {
+        let keys = Keys {
+            next: "tab".parse().unwrap(),
+            add_row: "alt-a".parse().unwrap(),
+            ..Keys::default()
+        };
+        let mut app = SnippetEngine::new("In", "IN ({*id:{id}*, }) {}").with_keys(keys);
+        app.handle_input(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT))
+            .unwrap();
+        let active = app.manager.active_index;
+        app.handle_input(KeyCode::Enter).unwrap();
+        assert_eq!(app.manager.active_index, active);
+        app.handle_input(KeyCode::Tab).unwrap();
+        assert_ne!(app.manager.active_index, active);
+        assert_eq!("IN (, ) ".to_owned(), app.manager.text());
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
 }
@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Number of backups kept when none is given.
+pub const DEFAULT_BACKUPS: usize = 3;
+
+/// Writes a file so it is either fully written or left as it was, even when the program
+/// is stopped halfway. The content is written to a hidden file next to it which is then
+/// renamed over the file, keeping its permissions. The previous content is kept in up to
+/// `backups` rotated copies, `.bak.1` being the most recent. A symlink is followed so the
+/// file it points to is replaced instead of the link.
+pub fn write(path: &Path, content: &str, backups: usize) -> Result<()> {
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let temp = sibling(path, ".", ".tmp");
+    let written = write_temp(&temp, path, content);
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.with_context(|| format!("{path:?} could not be written"))?;
+
+    // The backups are only rotated once the file is replaced, so a failed write leaves
+    // them as they were
+    let previous = (backups > 0 && path.exists()).then(|| sibling(path, ".", ".bak"));
+    if let Some(previous) = &previous {
+        if let Err(error) = fs::copy(path, previous) {
+            let _ = fs::remove_file(previous);
+            let _ = fs::remove_file(&temp);
+            return Err(error).with_context(|| format!("{path:?} could not be backed up"));
+        }
+    }
+
+    if let Err(error) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        if let Some(previous) = &previous {
+            let _ = fs::remove_file(previous);
+        }
+        return Err(error).with_context(|| format!("{path:?} could not be replaced"));
+    }
+    if let Some(previous) = &previous {
+        rotate_backups(path, previous, backups)
+            .with_context(|| format!("{path:?} could not be backed up"))?;
+    }
+    sync_directory(path).with_context(|| format!("{path:?} could not be synced"))?;
+    Ok(())
+}
+
+/// Writes the hidden file, created with the permissions of the file so it's never
+/// readable by more users than the file itself.
+fn write_temp(temp: &Path, path: &Path, content: &str) -> Result<()> {
+    // Left over by a write which was stopped, it would keep its own permissions
+    let _ = fs::remove_file(temp);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    let permissions = fs::metadata(path).map(|m| m.permissions()).ok();
+    #[cfg(unix)]
+    if let Some(permissions) = &permissions {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(permissions.mode() & 0o777);
+    }
+
+    let mut file = options.open(temp)?;
+    file.write_all(content.as_bytes())?;
+    if let Some(permissions) = permissions {
+        file.set_permissions(permissions)?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Syncs the directory of the file so the rename itself survives a crash, directories
+/// can't be opened as files on Windows.
+fn sync_directory(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(directory)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Shifts every backup up by one, dropping the oldest, and moves the copy of the previous
+/// content to `.bak.1`.
+fn rotate_backups(path: &Path, previous: &Path, backups: usize) -> Result<()> {
+    let backup = |n: usize| sibling(path, "", &format!(".bak.{n}"));
+
+    let oldest = backup(backups);
+    if oldest.exists() {
+        fs::remove_file(oldest)?;
+    }
+    for n in (1..backups).rev() {
+        let from = backup(n);
+        if from.exists() {
+            fs::rename(from, backup(n + 1))?;
+        }
+    }
+
+    fs::rename(previous, backup(1))?;
+    Ok(())
+}
+
+/// A path in the same directory as the file, named after it.
+pub fn sibling(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::write;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn rotates_backups() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        for content in ["one", "two", "three", "four"] {
+            write(&path, content, 2).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("snippets.json"), "four");
+        assert_eq!(read("snippets.json.bak.1"), "three");
+        assert_eq!(read("snippets.json.bak.2"), "two");
+        assert!(!dir.path().join("snippets.json.bak.3").exists());
+        assert!(!dir.path().join(".snippets.json.tmp").exists());
+        assert!(!dir.path().join(".snippets.json.bak").exists());
+    }
+
+    #[test]
+    fn keeps_backups_when_the_file_is_not_replaced() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        // A directory in place of the file can neither be backed up nor replaced
+        fs::create_dir_all(path.join("inside")).unwrap();
+        fs::write(dir.path().join("snippets.json.bak.1"), "one").unwrap();
+        assert!(write(&path, "two", 2).is_err());
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("snippets.json.bak.1"), "one");
+        assert!(!dir.path().join("snippets.json.bak.2").exists());
+        assert!(!dir.path().join(".snippets.json.tmp").exists());
+        assert!(!dir.path().join(".snippets.json.bak").exists());
+    }
+
+    #[test]
+    fn no_backups_when_disabled() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        write(&path, "one", 0).unwrap();
+        write(&path, "two", 0).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let target = dir.path().join("snippets.json");
+        let link = dir.path().join("link.json");
+        fs::write(&target, "one").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write(&link, "two", 1).unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "two");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("snippets.json.bak.1")).unwrap(),
+            "one"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        fs::write(&path, "one").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write(&path, "two", 1).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,557 @@
+use super::atomic;
+use crate::{snippet::Snippet, Snippets};
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+/// A directory where every file is a snippet. The title is the name of the file or a
+/// `title:` in its front-matter, and subdirectories are categories written before the
+/// title such as `docker/run`. The front-matter can also hold the `description:`,
+/// `tags:`, `language:` and `aliases:` of the snippet.
+pub struct Directory {
+    root: PathBuf,
+    entries: HashMap<String, Entry>,
+}
+
+/// The file a snippet was read from.
+struct Entry {
+    path: PathBuf,
+}
+
+impl Directory {
+    pub fn new(root: &Path) -> Self {
+        Directory {
+            root: root.to_owned(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<Snippets> {
+        let mut files = Vec::new();
+        files_in(&self.root, &mut files)
+            .with_context(|| format!("{:?} could not be read", self.root))?;
+
+        let mut snippets = Snippets::new();
+        self.entries.clear();
+        for path in files {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("{path:?} is not a valid snippet file"))?;
+            let (title, snippet) = split_front_matter(&content);
+            let title = title.unwrap_or_else(|| file_title(&path));
+            let title = match self.category(&path) {
+                Some(category) => format!("{category}/{title}"),
+                None => title,
+            };
+
+            if let Some(other) = self.entries.get(&title) {
+                bail!("{:?} and {path:?} are both titled {title:?}", other.path)
+            }
+
+            snippets.insert(title.clone(), snippet);
+            self.entries.insert(title, Entry { path });
+        }
+
+        Ok(snippets)
+    }
+
+    /// A hash of every file of the directory, which changes when any snippet is added,
+    /// changed or removed.
+    pub fn fingerprint(&self) -> Result<u64> {
+        let mut files = Vec::new();
+        files_in(&self.root, &mut files)?;
+        let mut hasher = DefaultHasher::new();
+        for path in files {
+            path.hash(&mut hasher);
+            fs::read(&path)?.hash(&mut hasher);
+        }
+        Ok(hasher.finish())
+    }
+
+    /// Writes a snippet to its file, a renamed snippet is moved to the file of its new
+    /// title and category.
+    pub fn save(&mut self, title: &str, snippet: &Snippet, previous: Option<&str>) -> Result<()> {
+        let previous = previous.and_then(|p| self.entries.get(p).map(|e| (p, e)));
+        if let Some(entry) = self.entries.get(title) {
+            if previous.map(|(p, _)| p) != Some(title) {
+                bail!("{title:?} is already the snippet of {:?}", entry.path)
+            }
+        }
+        let path = match previous {
+            // Files keep their name as long as their title doesn't change
+            Some((previous, entry)) if previous == title => entry.path.clone(),
+            Some((_, entry)) => {
+                let mut path = self.path_for(title);
+                if let Some(extension) = entry.path.extension() {
+                    path.set_extension(extension);
+                }
+                path
+            }
+            None => self.path_for(title),
+        };
+
+        let previous_path = previous.map(|(_, e)| e.path.clone());
+        if path.exists() && previous_path.as_ref() != Some(&path) {
+            bail!("{path:?} already holds another snippet")
+        }
+
+        let name = title.rsplit('/').next().unwrap_or(title);
+        let list = |values: &[String]| {
+            let values = values.iter().map(|v| quote(v, true)).collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        };
+        let mut header = Vec::new();
+        if file_title(&path) != name {
+            header.push(format!("title: {}", quote(name, false)));
+        }
+        if let Some(description) = &snippet.description {
+            header.push(format!("description: {}", quote(description, false)));
+        }
+        if !snippet.tags.is_empty() {
+            header.push(format!("tags: {}", list(&snippet.tags)));
+        }
+        if let Some(language) = &snippet.language {
+            header.push(format!("language: {}", quote(language, false)));
+        }
+        if !snippet.aliases.is_empty() {
+            header.push(format!("aliases: {}", list(&snippet.aliases)));
+        }
+
+        let body = &snippet.body;
+        let content = match header.is_empty() {
+            true => body.to_owned(),
+            false => format!("---\n{}\n---\n{body}", header.join("\n")),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Backups would be read as snippets, the directory is better kept in git instead
+        atomic::write(&path, &content, 0)?;
+
+        if let Some((previous, _)) = previous {
+            let previous = previous.to_owned();
+            if let Some(entry) = self.entries.remove(&previous) {
+                if entry.path != path {
+                    fs::remove_file(&entry.path)?;
+                    remove_empty_dirs(&entry.path, &self.root);
+                }
+            }
+        }
+
+        self.entries.insert(title.to_owned(), Entry { path });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, title: &str) -> Result<()> {
+        let Some(entry) = self.entries.remove(title) else {
+            return Ok(());
+        };
+        fs::remove_file(&entry.path)
+            .with_context(|| format!("{:?} could not be removed", entry.path))?;
+        remove_empty_dirs(&entry.path, &self.root);
+        Ok(())
+    }
+
+    /// Categories of a file are the subdirectories it is in, separated by `/`.
+    fn category(&self, path: &Path) -> Option<String> {
+        let parent = path.parent()?.strip_prefix(&self.root).ok()?;
+        let category = parent
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        (!category.is_empty()).then_some(category)
+    }
+
+    /// The file of a new snippet, characters which can't be in a file name are replaced.
+    fn path_for(&self, title: &str) -> PathBuf {
+        let mut parts = title.split('/').map(file_name).collect::<Vec<_>>();
+        let name = parts.pop().unwrap_or_default();
+        let mut path = self.root.clone();
+        path.extend(parts.iter().filter(|p| !p.is_empty()));
+        path.push(name);
+        path
+    }
+}
+
+fn files_in(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.and_then(|e| Ok((e.path(), e.file_type()?))))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    // Hidden files such as `.git` are not snippets, and links to directories are left out
+    // since one pointing to a parent would be walked forever
+    for (path, file_type) in entries {
+        if path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            files_in(&path, files)?;
+        } else if !path.is_dir() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits a front-matter written between `---` lines from the body, returning its title
+/// and the snippet. A header with any other key than the ones of a snippet is part of
+/// the body, such as a YAML snippet starting with a `---` document marker.
+fn split_front_matter(content: &str) -> (Option<String>, Snippet) {
+    let Some((header, body)) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+        .and_then(|rest| {
+            rest.split_once("\n---\n")
+                .or_else(|| rest.split_once("\r\n---\r\n"))
+        })
+    else {
+        return (None, Snippet::new(content));
+    };
+
+    let mut title = None;
+    let mut snippet = Snippet::new(body);
+    for line in header.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            return (None, Snippet::new(content));
+        };
+        match key.trim() {
+            "title" => title = Some(unquote(value)),
+            "description" => snippet.description = Some(unquote(value)),
+            "tags" => snippet.tags = list(value),
+            "language" => snippet.language = Some(unquote(value)),
+            "aliases" => snippet.aliases = list(value),
+            _ => return (None, Snippet::new(content)),
+        }
+    }
+
+    (title, snippet)
+}
+
+/// Reads a list written as `[a, b]` or `a, b`, commas inside quoted values such as
+/// `["a, b", c]` are part of the value.
+fn list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+
+    let mut values = vec![String::new()];
+    let mut quote = None;
+    let mut escaped = false;
+    for c in value.chars() {
+        let current = values.last_mut().unwrap();
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == ',' => {
+                values.push(String::new());
+                continue;
+            }
+            None if matches!(c, '"' | '\'') && current.trim().is_empty() => quote = Some(c),
+            None => {}
+        }
+        current.push(c);
+    }
+
+    values
+        .iter()
+        .map(|v| unquote(v))
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Reads a value which is plain, quoted with `'` where `''` is a quote, or quoted with
+/// `"` where a backslash escapes a quote, a backslash or a new line as `\n`.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let quoted = |q| {
+        value
+            .strip_prefix(q)
+            .and_then(|v| v.strip_suffix(q))
+            .filter(|_| value.len() > 1)
+    };
+
+    if let Some(inner) = quoted('\'') {
+        return inner.replace("''", "'");
+    }
+    let Some(inner) = quoted('"') else {
+        return value.to_owned();
+    };
+
+    let mut unescaped = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let escaped = match c {
+            '\\' => chars.next(),
+            _ => {
+                unescaped.push(c);
+                continue;
+            }
+        };
+        match escaped {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(c @ ('"' | '\\')) => unescaped.push(c),
+            Some(c) => unescaped.extend(['\\', c]),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Writes a value so `unquote` reads it back, values which would be read differently
+/// are quoted with `"`. Values of a list are also quoted when they hold a comma.
+fn quote(value: &str, listed: bool) -> String {
+    let plain = !value.is_empty()
+        && value.trim() == value
+        && !value.starts_with(['"', '\'', '['])
+        && !value.contains(|c: char| c.is_control())
+        && !value.contains(": ")
+        && (!listed || !value.contains([',', ']']));
+    if plain {
+        return value.to_owned();
+    }
+
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The title a file gets from its name, without its extension.
+fn file_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn file_name(title: &str) -> String {
+    let name = title
+        .chars()
+        .map(|c| match c {
+            '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '.' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    match name.trim() {
+        "" => "snippet".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+/// Removes the directories left empty by a moved file, up to the root of the store.
+fn remove_empty_dirs(path: &Path, root: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Directory;
+    use crate::snippet::Snippet;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn reads_files_as_snippets() {
+        let dir = store(&[
+            ("ls", "ls -la {path}"),
+            ("docker/run.sh", "docker run {image}"),
+            (
+                "docker/compose/up",
+                "---\ntitle: \"Start: all\"\n---\ndocker compose up",
+            ),
+            (".git/config", "ignored"),
+        ]);
+        let snippets = Directory::new(dir.path()).load().unwrap();
+        assert_eq!(snippets.len(), 3);
+        assert_eq!(snippets["ls"].body, "ls -la {path}");
+        assert_eq!(snippets["docker/run"].body, "docker run {image}");
+        assert_eq!(
+            snippets["docker/compose/Start: all"].body,
+            "docker compose up"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_links_to_directories() {
+        let dir = store(&[("ls", "ls -la"), ("docker/run", "docker run {image}")]);
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("docker/up")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("ls"), dir.path().join("list")).unwrap();
+        let snippets = Directory::new(dir.path()).load().unwrap();
+        let titles = snippets.keys().collect::<Vec<_>>();
+        assert_eq!(titles, vec!["docker/run", "list", "ls"]);
+    }
+
+    #[test]
+    fn rejects_duplicate_titles() {
+        let dir = store(&[("run.sh", "a"), ("run.txt", "b")]);
+        assert!(Directory::new(dir.path()).load().is_err());
+    }
+
+    #[test]
+    fn adds_files() {
+        let dir = store(&[]);
+        let mut directory = Directory::new(dir.path());
+        directory.load().unwrap();
+        directory
+            .save("docker/run", &Snippet::new("docker run {image}"), None)
+            .unwrap();
+        directory
+            .save("What? Why", &Snippet::new("body"), None)
+            .unwrap();
+
+        let run = fs::read_to_string(dir.path().join("docker/run")).unwrap();
+        assert_eq!(run, "docker run {image}");
+        let why = fs::read_to_string(dir.path().join("What_ Why")).unwrap();
+        assert_eq!(why, "---\ntitle: What? Why\n---\nbody");
+        let snippets = Directory::new(dir.path()).load().unwrap();
+        assert_eq!(snippets["What? Why"].body, "body");
+    }
+
+    #[test]
+    fn reads_metadata() {
+        let dir = store(&[(
+            "count.sql",
+            "---\ndescription: \"Count: rows\"\ntags: [db, 'sql']\nlanguage: sql\naliases: rows, total\n---\nSELECT count(*)",
+        )]);
+        let snippets = Directory::new(dir.path()).load().unwrap();
+        let count = &snippets["count"];
+        assert_eq!(count.body, "SELECT count(*)");
+        assert_eq!(count.description, Some("Count: rows".to_owned()));
+        assert_eq!(count.tags, vec!["db", "sql"]);
+        assert_eq!(count.language, Some("sql".to_owned()));
+        assert_eq!(count.aliases, vec!["rows", "total"]);
+    }
+
+    #[test]
+    fn round_trips_metadata() {
+        let dir = store(&[]);
+        let mut directory = Directory::new(dir.path());
+        directory.load().unwrap();
+        let snippet = Snippet {
+            description: Some("Note: \"quoted\" C:\\path\nsecond line ".to_owned()),
+            tags: vec!["a, b".to_owned(), "[c]".to_owned(), "'d'".to_owned()],
+            language: Some("'sql".to_owned()),
+            aliases: vec!["x: y".to_owned(), "it's".to_owned()],
+            ..Snippet::new("SELECT 1")
+        };
+        directory.save("a: b", &snippet, None).unwrap();
+
+        let snippets = Directory::new(dir.path()).load().unwrap();
+        assert_eq!(snippets["a: b"], snippet);
+        let content = fs::read_to_string(dir.path().join("a_ b")).unwrap();
+        assert!(content.starts_with("---\ntitle: \"a: b\"\ndescription: \"Note: \\\"quoted"));
+    }
+
+    #[test]
+    fn edits_in_place() {
+        let dir = store(&[("run.sh", "---\ntags: docker\n---\nold")]);
+        let mut directory = Directory::new(dir.path());
+        let mut snippets = directory.load().unwrap();
+        let run = &mut snippets["run"];
+        run.body = "new".to_owned();
+        run.language = Some("bash".to_owned());
+        directory.save("run", run, Some("run")).unwrap();
+        let content = fs::read_to_string(dir.path().join("run.sh")).unwrap();
+        assert_eq!(content, "---\ntags: [docker]\nlanguage: bash\n---\nnew");
+    }
+
+    #[test]
+    fn other_headers_are_part_of_the_body() {
+        let pod = "---\napiVersion: v1\nkind: Pod\n---\nkind: Service";
+        let dir = store(&[
+            ("k8s.yaml", pod),
+            ("notes", "---\ntitle: a\nnot a key\n---\nb"),
+        ]);
+        let snippets = Directory::new(dir.path()).load().unwrap();
+        assert_eq!(snippets["k8s"].body, pod);
+        assert_eq!(snippets["k8s"].tags, Vec::<String>::new());
+        assert_eq!(snippets["notes"].body, "---\ntitle: a\nnot a key\n---\nb");
+    }
+
+    #[test]
+    fn renames_files() {
+        let dir = store(&[("old/run.sh", "docker run")]);
+        let mut directory = Directory::new(dir.path());
+        directory.load().unwrap();
+        directory
+            .save("docker/start", &Snippet::new("docker run"), Some("old/run"))
+            .unwrap();
+
+        assert!(!dir.path().join("old").exists());
+        let content = fs::read_to_string(dir.path().join("docker/start.sh")).unwrap();
+        assert_eq!(content, "docker run");
+    }
+
+    #[test]
+    fn removes_files() {
+        let dir = store(&[("docker/run", "docker run"), ("ls", "ls")]);
+        let mut directory = Directory::new(dir.path());
+        directory.load().unwrap();
+        directory.remove("docker/run").unwrap();
+        assert!(!dir.path().join("docker").exists());
+        assert!(dir.path().join("ls").exists());
+    }
+
+    #[test]
+    fn refuses_to_overwrite_other_snippets() {
+        let dir = store(&[("a", "a"), ("b", "b")]);
+        let mut directory = Directory::new(dir.path());
+        directory.load().unwrap();
+        assert!(directory.save("b", &Snippet::new("new"), None).is_err());
+        assert_eq!(fs::read_to_string(dir.path().join("b")).unwrap(), "b");
+    }
+
+    #[test]
+    fn refuses_a_second_file_for_a_title() {
+        let dir = store(&[("run.sh", "a")]);
+        let mut directory = Directory::new(dir.path());
+        directory.load().unwrap();
+        assert!(directory.save("run", &Snippet::new("b"), None).is_err());
+        directory
+            .save("run", &Snippet::new("b"), Some("run"))
+            .unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert_eq!(Directory::new(dir.path()).load().unwrap()["run"].body, "b");
+    }
+
+    fn store(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+}
@@ -0,0 +1,611 @@
+use crate::{snippet::Snippet, Snippets};
+use anyhow::Result;
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::ser::PrettyFormatter;
+use std::{fmt, path::Path};
+use toml_edit::DocumentMut;
+
+/// Indentation of files which don't have any yet.
+pub const DEFAULT_INDENT: &str = "    ";
+
+/// File format snippets are stored in, a map of titles to snippets in each of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// Picks the format from the extension of the file, anything else is read as JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Format::Toml,
+            Some(extension)
+                if extension.eq_ignore_ascii_case("yaml")
+                    || extension.eq_ignore_ascii_case("yml") =>
+            {
+                Format::Yaml
+            }
+            _ => Format::Json,
+        }
+    }
+
+    pub fn read(self, content: &str) -> Result<Snippets> {
+        let snippets = match self {
+            Format::Json => serde_json::from_str(content)?,
+            Format::Toml => toml::from_str(content)?,
+            Format::Yaml => serde_norway::from_str(content)?,
+        };
+
+        Ok(snippets)
+    }
+
+    /// Writes snippets in their order, JSON is indented with the given indentation so
+    /// rewriting a file written by snippset only changes the snippets which changed.
+    pub fn write(self, snippets: &Snippets, indent: &str) -> Result<String> {
+        let content = match self {
+            Format::Json => write_json(snippets, indent)?,
+            Format::Toml => write_toml(snippets),
+            Format::Yaml => serde_norway::to_string(snippets)?,
+        };
+
+        Ok(content)
+    }
+
+    /// Writes snippets over the previous content of their file, keeping the text of the
+    /// snippets which didn't change along with the comments and formatting around them.
+    pub fn rewrite(self, previous: &str, snippets: &Snippets, indent: &str) -> Result<String> {
+        match self {
+            Format::Json => rewrite_json(previous, snippets, indent),
+            Format::Toml => rewrite_toml(previous, snippets),
+            Format::Yaml => rewrite_yaml(previous, snippets),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Json => write!(f, "JSON"),
+            Format::Toml => write!(f, "TOML"),
+            Format::Yaml => write!(f, "YAML"),
+        }
+    }
+}
+
+/// The indentation used by a file, taken from its first indented line.
+pub fn detect_indent(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find(|line| line.starts_with([' ', '\t']) && !line.trim().is_empty())
+        .map(|line| line[..line.len() - line.trim_start().len()].to_owned())
+}
+
+fn write_json(snippets: &Snippets, indent: &str) -> Result<String> {
+    let mut content = Vec::new();
+    let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut content, formatter);
+    snippets.serialize(&mut serializer)?;
+    content.push(b'\n');
+    Ok(String::from_utf8(content)?)
+}
+
+/// Writes snippets as TOML where multi-line snippets are written as `'''` literal
+/// strings, so they can be edited by hand without escaping. Snippets with metadata are
+/// written as an inline table, which keeps every snippet on its own line in order.
+fn write_toml(snippets: &Snippets) -> String {
+    snippets
+        .iter()
+        .map(|(title, snippet)| format!("{} = {}\n", toml_key(title), toml_snippet(snippet)))
+        .collect()
+}
+
+/// Rebuilds the TOML document in the order of the snippets, where the entries of
+/// unchanged snippets are kept as they were written and the others are written anew.
+fn rewrite_toml(previous: &str, snippets: &Snippets) -> Result<String> {
+    let kept = Format::Toml.read(previous)?;
+    let mut document = previous.parse::<DocumentMut>()?;
+    let entries = document.as_table().clone();
+    let table = document.as_table_mut();
+    table.clear();
+    for (title, snippet) in snippets {
+        let (key, item) = match entries.get_key_value(title) {
+            Some((key, item)) if kept.get(title) == Some(snippet) => (key.clone(), item.clone()),
+            found => {
+                let written = write_toml(&Snippets::from([(title.clone(), snippet.clone())]))
+                    .parse::<DocumentMut>()?;
+                let (key, item) = written.as_table().get_key_value(title).unwrap();
+                // A changed snippet keeps the comments written above it
+                (found.map_or(key, |(key, _)| key).clone(), item.clone())
+            }
+        };
+        table.insert_formatted(&key, item);
+    }
+
+    Ok(document.to_string())
+}
+
+/// The text of a file split around its entries, each entry keeps the text written
+/// before it such as separators or comments.
+struct Layout<'a> {
+    head: &'a str,
+    entries: IndexMap<String, (&'a str, &'a str)>,
+    tail: &'a str,
+}
+
+/// Rebuilds the JSON object in the order of the snippets, where the entries of unchanged
+/// snippets are kept as they were written and the others are written anew. Files which
+/// are written on a single line get their new entries on that line as well.
+fn rewrite_json(previous: &str, snippets: &Snippets, indent: &str) -> Result<String> {
+    let kept = Format::Json.read(previous)?;
+    let layout = match json_layout(previous) {
+        Some(layout) if !layout.entries.is_empty() => layout,
+        _ => return write_json(snippets, indent),
+    };
+    let leading = layout.entries[0].0;
+    let separator = match layout.entries.len() {
+        1 if leading.contains('\n') => format!(",{leading}"),
+        1 => ", ".to_owned(),
+        len => layout.entries[len - 1].0.to_owned(),
+    };
+
+    let mut content = layout.head.to_owned();
+    for (index, (title, snippet)) in snippets.iter().enumerate() {
+        let found = layout.entries.get_full(title);
+        content.push_str(match found {
+            _ if index == 0 => leading,
+            Some((position, _, (gap, _))) if position > 0 => gap,
+            _ => &separator,
+        });
+        match found {
+            Some((_, _, (_, text))) if kept.get(title) == Some(snippet) => content.push_str(text),
+            _ if !leading.contains('\n') => content.push_str(&format!(
+                "{}: {}",
+                serde_json::to_string(title)?,
+                serde_json::to_string(snippet)?
+            )),
+            _ => {
+                let entry = Snippets::from([(title.clone(), snippet.clone())]);
+                let written = write_json(&entry, indent)?;
+                content.push_str(&written[2 + indent.len()..written.len() - 3]);
+            }
+        }
+    }
+    content.push_str(layout.tail);
+
+    Ok(content)
+}
+
+/// Splits a JSON object into its entries, or `None` when the content isn't an object.
+fn json_layout(content: &str) -> Option<Layout<'_>> {
+    let bytes = content.as_bytes();
+    let skip_space = |mut i: usize| {
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        i
+    };
+
+    let open = skip_space(0);
+    if bytes.get(open) != Some(&b'{') {
+        return None;
+    }
+    let head = &content[..open + 1];
+    let mut entries = IndexMap::new();
+    let mut gap = open + 1;
+    let mut start = skip_space(gap);
+    if bytes.get(start) == Some(&b'}') {
+        return Some(Layout {
+            head,
+            entries,
+            tail: &content[gap..],
+        });
+    }
+
+    loop {
+        let key = json_value_end(bytes, start)?;
+        let title = serde_json::from_str::<String>(&content[start..key]).ok()?;
+        let colon = skip_space(key);
+        if bytes.get(colon) != Some(&b':') {
+            return None;
+        }
+        let end = json_value_end(bytes, skip_space(colon + 1))?;
+        entries.insert(title, (&content[gap..start], &content[start..end]));
+
+        let next = skip_space(end);
+        match bytes.get(next) {
+            Some(b',') => {
+                gap = end;
+                start = skip_space(next + 1);
+            }
+            Some(b'}') => {
+                return Some(Layout {
+                    head,
+                    entries,
+                    tail: &content[end..],
+                })
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// The end of the JSON value starting at `start`, skipping over strings and nested values.
+fn json_value_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut string = false;
+    let mut i = start;
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b'\\' if string => i += 1,
+            b'"' if string => {
+                string = false;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ if string => {}
+            b'"' => string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            b',' | b'}' | b']' if depth == 0 => return (i > start).then_some(i),
+            b if b.is_ascii_whitespace() && depth == 0 => return (i > start).then_some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    (depth == 0 && !string && i > start).then_some(i)
+}
+
+/// Rebuilds the YAML mapping in the order of the snippets, where the entries of unchanged
+/// snippets are kept as they were written and the others are written anew. A changed
+/// snippet keeps the comments written above it.
+fn rewrite_yaml(previous: &str, snippets: &Snippets) -> Result<String> {
+    let kept = Format::Yaml.read(previous)?;
+    let Some(layout) = yaml_layout(previous, &kept) else {
+        return Format::Yaml.write(snippets, DEFAULT_INDENT);
+    };
+
+    let mut content = layout.head.to_owned();
+    for (title, snippet) in snippets {
+        let found = layout.entries.get(title);
+        if let Some((decor, _)) = found {
+            content.push_str(decor);
+        }
+        match found {
+            Some((_, text)) if kept.get(title) == Some(snippet) => {
+                content.push_str(text);
+                if !text.ends_with('\n') {
+                    content.push('\n');
+                }
+            }
+            _ => content.push_str(&serde_norway::to_string(&Snippets::from([(
+                title.clone(),
+                snippet.clone(),
+            )]))?),
+        }
+    }
+    content.push_str(layout.tail);
+
+    Ok(content)
+}
+
+/// Splits a YAML mapping into its entries, an entry starts at an unindented line and the
+/// comments and blank lines at its end belong to the entry after it. `None` is returned
+/// when an entry can't be read on its own as the snippet it was read as, such as for
+/// flow mappings or anchors shared between entries.
+fn yaml_layout<'a>(content: &'a str, kept: &Snippets) -> Option<Layout<'a>> {
+    let mut offset = 0;
+    let mut head = 0;
+    let mut starts = Vec::new();
+    for line in content.split_inclusive('\n') {
+        let decor = line.trim().is_empty() || line.starts_with('#');
+        if starts.is_empty() && (line.starts_with("---") || line.starts_with('%')) {
+            head = offset + line.len();
+        } else if !decor && !line.starts_with([' ', '\t']) {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+
+    // Comments and blank lines at the end of an entry
+    let trailing = |text: &str| {
+        let mut end = text.len();
+        for line in text.split_inclusive('\n').rev() {
+            if !line.trim().is_empty() && !line.starts_with('#') {
+                break;
+            }
+            end -= line.len();
+        }
+        end
+    };
+
+    let mut entries = IndexMap::new();
+    let mut decor = head;
+    for (index, &start) in starts.iter().enumerate() {
+        let next = starts.get(index + 1).copied().unwrap_or(content.len());
+        let end = start + trailing(&content[start..next]).max(1);
+        let text = &content[start..end];
+        let mut read = serde_norway::from_str::<Snippets>(text).ok()?;
+        let (title, snippet) = read.pop().filter(|_| read.is_empty())?;
+        if kept.get(&title) != Some(&snippet) || entries.contains_key(&title) {
+            return None;
+        }
+        entries.insert(title, (&content[decor..start], text));
+        decor = end;
+    }
+    if starts.is_empty() || !entries.keys().eq(kept.keys()) {
+        return None;
+    }
+
+    Some(Layout {
+        head: &content[..head],
+        entries,
+        tail: &content[decor..],
+    })
+}
+
+fn toml_snippet(snippet: &Snippet) -> String {
+    if !snippet.has_metadata() {
+        return toml_string(&snippet.body);
+    }
+
+    let list = |values: &[String]| {
+        let values = values.iter().map(|v| toml_string(v)).collect::<Vec<_>>();
+        format!("[{}]", values.join(", "))
+    };
+    let mut fields = vec![format!("body = {}", toml_string(&snippet.body))];
+    if let Some(description) = &snippet.description {
+        fields.push(format!("description = {}", toml_string(description)));
+    }
+    if !snippet.tags.is_empty() {
+        fields.push(format!("tags = {}", list(&snippet.tags)));
+    }
+    if let Some(language) = &snippet.language {
+        fields.push(format!("language = {}", toml_string(language)));
+    }
+    if !snippet.aliases.is_empty() {
+        fields.push(format!("aliases = {}", list(&snippet.aliases)));
+    }
+
+    format!("{{ {} }}", fields.join(", "))
+}
+
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match bare {
+        true => key.to_owned(),
+        false => toml::Value::String(key.to_owned()).to_string(),
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    // Literal strings have no escapes, so they can't hold their own delimiter, control
+    // characters or a quote right before the closing delimiter.
+    let literal = value.contains('\n')
+        && !value.contains("'''")
+        && !value.ends_with('\'')
+        && value
+            .chars()
+            .all(|c| c == '\n' || c == '\t' || !c.is_control());
+    match literal {
+        true => format!("'''\n{value}'''"),
+        false => toml::Value::String(value.to_owned()).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect_indent, Format, DEFAULT_INDENT};
+    use crate::{snippet::Snippet, Snippets};
+    use std::path::Path;
+
+    #[test]
+    fn picks_format_from_extension() {
+        assert_eq!(Format::from_path(Path::new("a.toml")), Format::Toml);
+        assert_eq!(Format::from_path(Path::new("a.YML")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new("a.yaml")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new("a.json")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("snippets")), Format::Json);
+    }
+
+    #[test]
+    fn reads_toml() {
+        let snippets = Format::Toml
+            .read("server = '''\nlisten {port:8080};\n'''\n\"git log\" = \"git log -{n}\"")
+            .unwrap();
+        assert_eq!(snippets["server"].body, "listen {port:8080};\n");
+        assert_eq!(snippets["git log"].body, "git log -{n}");
+    }
+
+    #[test]
+    fn reads_yaml() {
+        let snippets = Format::Yaml
+            .read("server: |\n  listen {port:8080};\n  root {root};\ngit log: git log -{n}\n")
+            .unwrap();
+        assert_eq!(
+            snippets["server"].body,
+            "listen {port:8080};\nroot {root};\n"
+        );
+        assert_eq!(snippets["git log"].body, "git log -{n}");
+    }
+
+    #[test]
+    fn writes_multi_line_toml_literals() {
+        let content = Format::Toml
+            .write(
+                &snippets(&[("server", "listen {port:8080};\n\\d")]),
+                DEFAULT_INDENT,
+            )
+            .unwrap();
+        assert_eq!(content, "server = '''\nlisten {port:8080};\n\\d'''\n");
+    }
+
+    #[test]
+    fn writes_yaml_block_scalars() {
+        let content = Format::Yaml
+            .write(
+                &snippets(&[("server", "listen {port};\nroot {root};")]),
+                DEFAULT_INDENT,
+            )
+            .unwrap();
+        assert_eq!(content, "server: |-\n  listen {port};\n  root {root};\n");
+    }
+
+    #[test]
+    fn keeps_order_of_every_format() {
+        let titles = ["b", "a", "c"];
+        for (format, content) in [
+            (Format::Json, r#"{"b": "", "a": "", "c": ""}"#),
+            (Format::Toml, "b = ''\na = ''\nc = ''"),
+            (Format::Yaml, "b: ''\na: ''\nc: ''"),
+        ] {
+            let snippets = format.read(content).unwrap();
+            assert_eq!(snippets.keys().collect::<Vec<_>>(), titles, "{format}");
+            let written = format
+                .read(&format.write(&snippets, "  ").unwrap())
+                .unwrap();
+            assert_eq!(written.keys().collect::<Vec<_>>(), titles, "{format}");
+        }
+    }
+
+    #[test]
+    fn writes_indented_json() {
+        let snippets = snippets(&[("b", "{x}"), ("a", "line\nline")]);
+        assert_eq!(
+            Format::Json.write(&snippets, "\t").unwrap(),
+            "{\n\t\"b\": \"{x}\",\n\t\"a\": \"line\\nline\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn detects_indentation() {
+        assert_eq!(detect_indent("{\n  \"a\": \"b\"\n}"), Some("  ".to_owned()));
+        assert_eq!(
+            detect_indent("{\n\n\t\"a\": \"b\"\n}"),
+            Some("\t".to_owned())
+        );
+        assert_eq!(detect_indent("{\"a\": \"b\"}"), None);
+    }
+
+    #[test]
+    fn writes_toml_metadata_inline() {
+        let mut snippets = snippets(&[("count", "SELECT count(*)\nFROM {table}")]);
+        snippets["count"].tags = vec!["db".to_owned()];
+        snippets["count"].language = Some("sql".to_owned());
+        let content = Format::Toml.write(&snippets, DEFAULT_INDENT).unwrap();
+        assert_eq!(
+            content,
+            "count = { body = '''\nSELECT count(*)\nFROM {table}''', tags = [\"db\"], language = \"sql\" }\n"
+        );
+    }
+
+    #[test]
+    fn rewrites_only_changed_toml_entries() {
+        let previous = "# Servers\nserver = '''\nlisten {port};\n'''  # nginx\n\n# Git\nlog = \"git log\"\nold = 'x'\n";
+        let mut snippets = Format::Toml.read(previous).unwrap();
+        snippets.shift_remove("old");
+        snippets["log"].body = "git log -{n}".to_owned();
+        snippets.insert("new".to_owned(), Snippet::new("n"));
+        assert_eq!(
+            Format::Toml.rewrite(previous, &snippets, DEFAULT_INDENT).unwrap(),
+            "# Servers\nserver = '''\nlisten {port};\n'''  # nginx\n\n# Git\nlog = \"git log -{n}\"\nnew = \"n\"\n"
+        );
+    }
+
+    #[test]
+    fn rewrites_only_changed_json_entries() {
+        let previous = "{\n  \"a\": {\"body\": \"y\", \"tags\": [\"t\"]},\n\n  \"b\":  \"x\",\n  \"c\": \"z\"\n}\n";
+        let mut snippets = Format::Json.read(previous).unwrap();
+        snippets.shift_remove("b");
+        snippets["c"].body = "w".to_owned();
+        snippets.insert("d".to_owned(), Snippet::new("n\"{x}\""));
+        assert_eq!(
+            Format::Json.rewrite(previous, &snippets, "  ").unwrap(),
+            "{\n  \"a\": {\"body\": \"y\", \"tags\": [\"t\"]},\n  \"c\": \"w\",\n  \"d\": \"n\\\"{x}\\\"\"\n}\n"
+        );
+
+        let previous = r#"{"a": "x", "b": "y"}"#;
+        let mut snippets = Format::Json.read(previous).unwrap();
+        snippets["a"].tags = vec!["t".to_owned()];
+        assert_eq!(
+            Format::Json.rewrite(previous, &snippets, "  ").unwrap(),
+            r#"{"a": {"body":"x","tags":["t"]}, "b": "y"}"#
+        );
+    }
+
+    #[test]
+    fn rewrites_only_changed_yaml_entries() {
+        let previous =
+            "---\n# team snippets\na: 'x'  # quoted\n\n# history\nb: |\n  y\nold: o\nc: z\n# end\n";
+        let mut snippets = Format::Yaml.read(previous).unwrap();
+        snippets.shift_remove("old");
+        snippets["c"].body = "w".to_owned();
+        snippets.insert("d".to_owned(), Snippet::new("n"));
+        assert_eq!(
+            Format::Yaml.rewrite(previous, &snippets, "  ").unwrap(),
+            "---\n# team snippets\na: 'x'  # quoted\n\n# history\nb: |\n  y\nc: w\nd: n\n# end\n"
+        );
+    }
+
+    #[test]
+    fn rewrites_unsplittable_files_anew() {
+        let previous = "a: &x 'y'\nb: *x\n";
+        let read = Format::Yaml.read(previous).unwrap();
+        assert_eq!(
+            Format::Yaml.rewrite(previous, &read, "  ").unwrap(),
+            "a: y\nb: y\n"
+        );
+
+        let previous = "{}";
+        assert_eq!(
+            Format::Json
+                .rewrite(previous, &snippets(&[("a", "x")]), "  ")
+                .unwrap(),
+            "{\n  \"a\": \"x\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        let mut snippets = snippets(&[
+            ("git log", "git log -{n}"),
+            ("server", "listen {port};\n"),
+            ("quotes", "it's '''\nquoted'"),
+            ("tabs", "\tindented\r\n{x}"),
+            ("", "empty title"),
+            ("tagged", "SELECT {x}"),
+        ]);
+        snippets["tagged"] = Snippet {
+            description: Some("Select \"x\"".to_owned()),
+            tags: vec!["db".to_owned(), "it's".to_owned()],
+            language: Some("sql".to_owned()),
+            aliases: vec!["sel".to_owned()],
+            ..Snippet::new("SELECT {x}\nFROM t")
+        };
+        for format in [Format::Json, Format::Toml, Format::Yaml] {
+            let content = format.write(&snippets, DEFAULT_INDENT).unwrap();
+            assert_eq!(format.read(&content).unwrap(), snippets, "{format}");
+        }
+    }
+
+    fn snippets(entries: &[(&str, &str)]) -> Snippets {
+        entries
+            .iter()
+            .map(|(title, body)| (title.to_string(), Snippet::new(body)))
+            .collect()
+    }
+}
@@ -0,0 +1,259 @@
+use super::Store;
+use crate::{
+    sections::{delimiters::Delimiters, parse_options::Syntax},
+    Snippets,
+};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Stores layered in priority order such as a system, a team and a personal one, where a
+/// snippet of a later layer replaces the one of an earlier layer with the same title.
+pub struct Layers {
+    layers: Vec<Layer>,
+}
+
+struct Layer {
+    store: Store,
+    /// Syntax of the snippets once loaded, none when the store doesn't exist yet.
+    syntax: Option<Syntax>,
+}
+
+/// The snippets of every layer merged together, along with the layer each comes from.
+pub struct Merged {
+    pub snippets: Snippets,
+    /// The snippets of each layer on their own.
+    stores: Vec<Snippets>,
+    sources: HashMap<String, usize>,
+    syntaxes: Vec<Option<Syntax>>,
+    delimiters: Vec<Delimiters>,
+    commands: Vec<bool>,
+}
+
+impl Merged {
+    /// The layer a snippet comes from.
+    pub fn source(&self, title: &str) -> usize {
+        self.sources[title]
+    }
+
+    /// The snippets of the store a snippet comes from, which its includes are taken from.
+    pub fn store(&self, title: &str) -> &Snippets {
+        &self.stores[self.source(title)]
+    }
+
+    pub fn syntax(&self, title: &str) -> Syntax {
+        self.syntaxes[self.source(title)].unwrap_or(Syntax::Snippset)
+    }
+
+    /// Delimiters of the store a snippet comes from.
+    pub fn delimiters(&self, title: &str) -> &Delimiters {
+        &self.delimiters[self.source(title)]
+    }
+
+    /// Whether the commands of the store a snippet comes from are run.
+    pub fn allows_commands(&self, title: &str) -> bool {
+        self.commands[self.source(title)]
+    }
+}
+
+impl Layers {
+    pub fn new(stores: Vec<Store>) -> Self {
+        let layers = stores
+            .into_iter()
+            .map(|store| Layer {
+                store,
+                syntax: None,
+            })
+            .collect();
+        Layers { layers }
+    }
+
+    /// Whether any of the layers exists.
+    pub fn exists(&self) -> bool {
+        self.layers.iter().any(|l| l.store.exists())
+    }
+
+    /// Loads every layer, a single layer must exist while layers which don't exist are
+    /// left out when there are several.
+    pub fn load(&mut self) -> Result<Merged> {
+        let single = self.layers.len() == 1;
+        let mut snippets = Snippets::new();
+        let mut stores = Vec::new();
+        let mut sources = HashMap::new();
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            layer.syntax = None;
+            if !single && !layer.store.exists() {
+                stores.push(Snippets::new());
+                continue;
+            }
+
+            let (loaded, syntax) = layer.store.load()?;
+            layer.syntax = Some(syntax);
+            for (title, snippet) in &loaded {
+                sources.insert(title.clone(), index);
+                // Overridden snippets are listed where they were first written
+                snippets.insert(title.clone(), snippet.clone());
+            }
+            stores.push(loaded);
+        }
+
+        if self.layers.iter().all(|l| l.syntax.is_none()) {
+            bail!("None of the snippet paths could be found")
+        }
+
+        Ok(Merged {
+            snippets,
+            stores,
+            sources,
+            syntaxes: self.layers.iter().map(|l| l.syntax).collect(),
+            delimiters: self
+                .layers
+                .iter()
+                .map(|l| l.store.delimiters().clone())
+                .collect(),
+            commands: self
+                .layers
+                .iter()
+                .map(|l| l.store.allows_commands())
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Name of a layer as shown to the user.
+    pub fn name(&self, index: usize) -> String {
+        self.layers[index].store.path().display().to_string()
+    }
+
+    /// Layers snippets can be saved to, leaving out VS Code snippet files and paths which
+    /// can't be written such as a system layer.
+    pub fn writable(&self) -> Vec<usize> {
+        let writable =
+            |layer: &Layer| layer.syntax != Some(Syntax::TextMate) && layer.store.writable();
+        (0..self.layers.len())
+            .filter(|&i| writable(&self.layers[i]))
+            .collect()
+    }
+
+    pub fn store_mut(&mut self, index: usize) -> &mut Store {
+        &mut self.layers[index].store
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Layers;
+    use crate::{includes, store::Store};
+    use std::{fs, path::Path};
+    use tempfile::TempDir;
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let dir = TempDir::new().unwrap();
+        let system = file(
+            dir.path(),
+            "system.json",
+            r#"{"a": "system a", "b": "system b"}"#,
+        );
+        let personal = file(
+            dir.path(),
+            "personal.json",
+            r#"{"b": "personal b", "c": "c"}"#,
+        );
+        let missing = dir.path().join("team.json");
+
+        let mut layers = layers(&[&system, &missing, &personal]);
+        let merged = layers.load().unwrap();
+        let titles = merged.snippets.keys().collect::<Vec<_>>();
+        assert_eq!(titles, vec!["a", "b", "c"]);
+        assert_eq!(merged.snippets["b"].body, "personal b");
+        assert_eq!(merged.source("a"), 0);
+        assert_eq!(merged.source("b"), 2);
+        assert_eq!(layers.writable(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn vscode_layers_are_not_writable() {
+        let dir = TempDir::new().unwrap();
+        let code = file(
+            dir.path(),
+            "rust.code-snippets",
+            r#"{"Print": {"prefix": "p", "body": "println!($1);"}}"#,
+        );
+        let personal = file(dir.path(), "personal.json", r#"{"a": "a"}"#);
+
+        let mut layers = layers(&[&code, &personal]);
+        layers.load().unwrap();
+        assert_eq!(layers.writable(), vec![1]);
+    }
+
+    #[test]
+    fn layers_keep_their_delimiters() {
+        let dir = TempDir::new().unwrap();
+        let plain = file(dir.path(), "plain.json", r#"{"a": "{a}"}"#);
+        let templates = file(dir.path(), "templates.json", r#"{"b": "{b} <<b>>"}"#);
+
+        let mut layers = Layers::new(vec![
+            Store::new(&plain, None, 0),
+            Store::new(&templates, None, 0).with_delimiters("<< >>".parse().unwrap()),
+        ]);
+        let merged = layers.load().unwrap();
+        assert_eq!(merged.delimiters("a").open, vec!['{']);
+        assert_eq!(merged.delimiters("b").open, vec!['<', '<']);
+    }
+
+    #[test]
+    fn commands_are_allowed_per_layer() {
+        let dir = TempDir::new().unwrap();
+        let team = file(dir.path(), "team.json", r#"{"a": "{$(whoami)}"}"#);
+        let personal = file(dir.path(), "personal.json", r#"{"b": "{$(whoami)}"}"#);
+
+        let mut layers = Layers::new(vec![
+            Store::new(&team, None, 0),
+            Store::new(&personal, None, 0).with_commands(true),
+        ]);
+        let merged = layers.load().unwrap();
+        assert!(!merged.allows_commands("a"));
+        assert!(merged.allows_commands("b"));
+    }
+
+    #[test]
+    fn includes_stay_within_their_layer() {
+        let dir = TempDir::new().unwrap();
+        let team = file(
+            dir.path(),
+            "team.json",
+            r#"{"head": "team", "a": "{>head} a"}"#,
+        );
+        let personal = file(
+            dir.path(),
+            "personal.json",
+            r#"{"head": "mine", "b": "{>head} b", "c": "{>a}"}"#,
+        );
+
+        let merged = layers(&[&team, &personal]).load().unwrap();
+        let expand = |title| includes::expand(title, merged.store(title), merged.delimiters(title));
+        assert_eq!(expand("a").unwrap(), "team a");
+        assert_eq!(expand("b").unwrap(), "mine b");
+        assert!(expand("c").is_err());
+    }
+
+    #[test]
+    fn single_layer_must_exist() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("snippets.json");
+        assert!(layers(&[&missing]).load().is_err());
+    }
+
+    fn file(dir: &Path, name: &str, content: &str) -> std::path::PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn layers(paths: &[&Path]) -> Layers {
+        Layers::new(paths.iter().map(|p| Store::new(p, None, 0)).collect())
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for another snippset to finish saving.
+const WAIT: Duration = Duration::from_secs(3);
+
+/// An advisory lock of the system on a lock file, other snippsets wait for it to be
+/// released before saving. The lock is released when dropped or when snippset stops,
+/// even when it crashes, so the file left behind doesn't keep anyone from saving.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("{path:?} could not be created"))?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Lock { _file: file }),
+                Err(TryLockError::WouldBlock) if start.elapsed() < WAIT => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    bail!("The snippets are being saved by another snippset")
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("{path:?} could not be locked"))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lock;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn is_released_when_dropped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".snippets.json.lock");
+        let lock = Lock::acquire(&path).unwrap();
+        assert!(path.exists());
+        drop(lock);
+        assert!(Lock::acquire(&path).is_ok());
+    }
+
+    #[test]
+    fn fails_while_held() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".snippets.json.lock");
+        let _lock = Lock::acquire(&path).unwrap();
+        assert!(Lock::acquire(&path).is_err());
+    }
+
+    #[test]
+    fn ignores_files_left_behind() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".snippets.json.lock");
+        fs::write(&path, "12345").unwrap();
+        assert!(Lock::acquire(&path).is_ok());
+    }
+}
@@ -0,0 +1,422 @@
+mod atomic;
+mod directory;
+mod format;
+mod layers;
+mod lock;
+mod vscode;
+
+use crate::{
+    sections::{delimiters::Delimiters, parse_options::Syntax},
+    snippet::Snippet,
+    Snippets,
+};
+use anyhow::{bail, Context, Result};
+use directory::Directory;
+use std::{
+    fs::{self, OpenOptions},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+};
+
+pub use atomic::DEFAULT_BACKUPS;
+pub use format::{Format, DEFAULT_INDENT};
+pub use layers::{Layers, Merged};
+pub use lock::Lock;
+
+/// Where snippets are kept, a single file in one of the formats or a directory where
+/// every file is a snippet.
+pub struct Store {
+    path: PathBuf,
+    kind: Kind,
+    /// Number of backups kept of a file when it is saved.
+    backups: usize,
+    /// Fingerprint of the snippets when they were last read or saved, to find out whether
+    /// someone else changed them since.
+    fingerprint: Option<u64>,
+    /// Placeholder delimiters the snippets of the store are written with.
+    delimiters: Delimiters,
+    /// Whether the commands of the store's placeholders are run.
+    commands: bool,
+}
+
+enum Kind {
+    /// A file along with the indentation it is written with.
+    File(Format, String),
+    Directory(Directory),
+}
+
+impl Store {
+    /// Opens the store at the path, a directory or a path ending with a separator is a
+    /// directory store, while files are read in the given format or the one of their
+    /// extension.
+    pub fn new(path: &Path, format: Option<Format>, backups: usize) -> Self {
+        let directory = path.is_dir() || path.to_string_lossy().ends_with(MAIN_SEPARATOR);
+        let kind = match directory {
+            true => Kind::Directory(Directory::new(path)),
+            false => Kind::File(
+                format.unwrap_or_else(|| Format::from_path(path)),
+                DEFAULT_INDENT.to_owned(),
+            ),
+        };
+
+        Store {
+            path: path.to_owned(),
+            kind,
+            backups,
+            fingerprint: None,
+            delimiters: Delimiters::default(),
+            commands: false,
+        }
+    }
+
+    pub fn with_delimiters(self, delimiters: Delimiters) -> Self {
+        Store { delimiters, ..self }
+    }
+
+    pub fn with_commands(self, commands: bool) -> Self {
+        Store { commands, ..self }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn delimiters(&self) -> &Delimiters {
+        &self.delimiters
+    }
+
+    pub fn allows_commands(&self) -> bool {
+        self.commands
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Whether snippets can be saved to the store, found out by opening the file and
+    /// creating a file next to it since permissions alone don't tell about read-only
+    /// mounts, access lists or files owned by someone else. A missing store can be saved
+    /// to when it could be created.
+    pub fn writable(&self) -> bool {
+        let directory = match &self.kind {
+            Kind::File(..) => match self.path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            },
+            Kind::Directory(_) => &self.path,
+        };
+        let file = match &self.kind {
+            Kind::File(..) if self.exists() => {
+                OpenOptions::new().write(true).open(&self.path).is_ok()
+            }
+            _ => true,
+        };
+
+        file && directory
+            .ancestors()
+            .find(|d| d.exists())
+            .is_some_and(can_create_in)
+    }
+
+    /// Loads snippets along with the syntax they are written in, VS Code snippet files are
+    /// read as they are.
+    pub fn load(&mut self) -> Result<(Snippets, Syntax)> {
+        let path = &self.path;
+        let (format, indent) = match &mut self.kind {
+            Kind::File(format, indent) => (*format, indent),
+            Kind::Directory(directory) => {
+                let snippets = directory.load()?;
+                self.fingerprint = Some(directory.fingerprint()?);
+                return Ok((snippets, Syntax::Snippset));
+            }
+        };
+
+        let content =
+            fs::read_to_string(path).with_context(|| format!("{path:?} could not be found"))?;
+        self.fingerprint = Some(hash(&content));
+        if let Some(detected) = format::detect_indent(&content) {
+            *indent = detected;
+        }
+        let code_snippets = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("code-snippets"));
+        if format == Format::Json {
+            if let Some(snippets) = vscode::parse(&content, code_snippets) {
+                return Ok((snippets, Syntax::TextMate));
+            }
+        }
+
+        let snippets = format
+            .read(&content)
+            .with_context(|| format!("{path:?} is not a valid snippet {format} format"))?;
+        Ok((snippets, Syntax::Snippset))
+    }
+
+    /// Loads snippets which are going to be changed, VS Code snippet files can only be used.
+    pub fn load_editable(&mut self) -> Result<Snippets> {
+        match self.load()? {
+            (snippets, Syntax::Snippset) => Ok(snippets),
+            (_, Syntax::TextMate) => bail!(
+                "{:?} is a VS Code snippet file, it can be used but not changed",
+                self.path
+            ),
+        }
+    }
+
+    /// Locks the store against other snippsets saving to it, until the lock is dropped.
+    /// The lock is taken next to the file a symlink points to, which is the one written,
+    /// so reaching the same store through a link or its target takes the same lock.
+    pub fn lock(&self) -> Result<Lock> {
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let path = match &self.kind {
+            Kind::File(..) => atomic::sibling(&canonical(&self.path), ".", ".lock"),
+            Kind::Directory(_) => {
+                fs::create_dir_all(&self.path)
+                    .with_context(|| format!("{:?} could not be created", self.path))?;
+                canonical(&self.path).join(".snippset.lock")
+            }
+        };
+        Lock::acquire(&path)
+    }
+
+    /// Whether the snippets were changed by someone else since they were read, a store
+    /// that was never read has changed when it exists.
+    pub fn changed(&self) -> Result<bool> {
+        let current = match &self.kind {
+            _ if !self.exists() => None,
+            Kind::File(..) => Some(hash(&fs::read_to_string(&self.path)?)),
+            Kind::Directory(directory) => Some(directory.fingerprint()?),
+        };
+        Ok(current != self.fingerprint)
+    }
+
+    /// Saves an added or edited snippet, a renamed snippet replaces its `previous` title
+    /// keeping its place while new ones are added at the end.
+    pub fn save(
+        &mut self,
+        snippets: &mut Snippets,
+        title: String,
+        snippet: Snippet,
+        previous: Option<&str>,
+    ) -> Result<()> {
+        self.insert(snippets, title, snippet, previous)?;
+        self.write(snippets)
+    }
+
+    /// Saves many snippets at once, replacing the ones with the same title.
+    pub fn save_all(&mut self, snippets: &mut Snippets, added: Snippets) -> Result<()> {
+        for (title, snippet) in added {
+            let previous = snippets.contains_key(&title).then(|| title.clone());
+            self.insert(snippets, title, snippet, previous.as_deref())?;
+        }
+        self.write(snippets)
+    }
+
+    pub fn remove(&mut self, snippets: &mut Snippets, title: &str) -> Result<()> {
+        if let Kind::Directory(directory) = &mut self.kind {
+            directory.remove(title)?;
+        }
+        snippets.shift_remove(title);
+        self.write(snippets)
+    }
+
+    fn insert(
+        &mut self,
+        snippets: &mut Snippets,
+        title: String,
+        snippet: Snippet,
+        previous: Option<&str>,
+    ) -> Result<()> {
+        if let Kind::Directory(directory) = &mut self.kind {
+            directory.save(&title, &snippet, previous)?;
+        }
+
+        match previous.and_then(|p| snippets.get_index_of(p)) {
+            Some(index) => {
+                snippets.shift_remove_index(index);
+                snippets.shift_insert(index, title, snippet);
+            }
+            None => {
+                snippets.insert(title, snippet);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the snippets of a file, the files of a directory are already written.
+    fn write(&mut self, snippets: &Snippets) -> Result<()> {
+        self.fingerprint = Some(match &self.kind {
+            Kind::File(format, indent) => {
+                let content = match fs::read_to_string(&self.path) {
+                    Ok(previous) => format.rewrite(&previous, snippets, indent)?,
+                    Err(_) => format.write(snippets, indent)?,
+                };
+                atomic::write(&self.path, &content, self.backups)?;
+                hash(&content)
+            }
+            Kind::Directory(directory) => directory.fingerprint()?,
+        });
+
+        Ok(())
+    }
+}
+
+/// Whether a file can be created in the directory, the file is removed right away.
+fn can_create_in(directory: &Path) -> bool {
+    let probe = directory.join(format!(".snippset-{}.probe", std::process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Store;
+    use crate::snippet::Snippet;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn edits_only_change_their_entry() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        let content = "{\n  \"b\": \"one\",\n  \"a\": \"two {x}\",\n  \"c\": \"three\"\n}\n";
+        fs::write(&path, content).unwrap();
+
+        let mut store = Store::new(&path, None, 0);
+        let mut snippets = store.load_editable().unwrap();
+        store
+            .save(&mut snippets, "A".to_owned(), Snippet::new("2 {x}"), Some("a"))
+            .unwrap();
+        store
+            .save(&mut snippets, "d".to_owned(), Snippet::new("four"), None)
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\n  \"b\": \"one\",\n  \"A\": \"2 {x}\",\n  \"c\": \"three\",\n  \"d\": \"four\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn detects_changes_by_others() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        let mut store = Store::new(&path, None, 0);
+        assert!(!store.changed().unwrap());
+
+        fs::write(&path, "{\"a\": \"one\"}").unwrap();
+        assert!(store.changed().unwrap());
+        let mut snippets = store.load_editable().unwrap();
+        assert!(!store.changed().unwrap());
+
+        store
+            .save(&mut snippets, "b".to_owned(), Snippet::new("two"), None)
+            .unwrap();
+        assert!(!store.changed().unwrap());
+        fs::write(&path, "{\"a\": \"1\"}").unwrap();
+        assert!(store.changed().unwrap());
+    }
+
+    #[test]
+    fn detects_changes_in_directories() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a"), "one").unwrap();
+        let mut store = Store::new(dir.path(), None, 0);
+        store.load_editable().unwrap();
+        let _lock = store.lock().unwrap();
+        assert!(!store.changed().unwrap());
+
+        fs::write(dir.path().join("b"), "two").unwrap();
+        assert!(store.changed().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn locks_the_target_of_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let real = dir.path().join("real");
+        fs::create_dir(&real).unwrap();
+        fs::write(real.join("snippets.json"), "{}").unwrap();
+        std::os::unix::fs::symlink(real.join("snippets.json"), dir.path().join("link.json"))
+            .unwrap();
+        std::os::unix::fs::symlink(&real, dir.path().join("linked")).unwrap();
+
+        let _lock = Store::new(&dir.path().join("link.json"), None, 0)
+            .lock()
+            .unwrap();
+        assert!(real.join(".snippets.json.lock").exists());
+        assert!(!dir.path().join(".link.json.lock").exists());
+
+        let _lock = Store::new(&dir.path().join("linked"), None, 0)
+            .lock()
+            .unwrap();
+        assert!(real.join(".snippset.lock").exists());
+    }
+
+    #[test]
+    fn removes_and_saves_many() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        fs::write(&path, "{\"a\": \"one\", \"b\": \"two\"}").unwrap();
+
+        let mut store = Store::new(&path, None, 0);
+        let mut snippets = store.load_editable().unwrap();
+        store.remove(&mut snippets, "a").unwrap();
+        let added = [("b", "2"), ("c", "3")]
+            .into_iter()
+            .map(|(title, body)| (title.to_owned(), Snippet::new(body)))
+            .collect();
+        store.save_all(&mut snippets, added).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"b\": \"2\", \"c\": \"3\"}"
+        );
+    }
+
+    #[test]
+    fn finds_out_whether_it_can_be_written() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        assert!(Store::new(&path, None, 0).writable());
+        assert!(Store::new(&dir.path().join("new/dir/"), None, 0).writable());
+
+        fs::write(&path, "{}").unwrap();
+        assert!(Store::new(&path, None, 0).writable());
+        assert!(!Store::new(&path.join("nested.json"), None, 0).writable());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn json_with_descriptions_is_not_vscode() {
+        let dir = TempDir::new().unwrap();
+        let content = r#"{"greet": {"body": "Hello {name}", "description": "Greeting"}}"#;
+        let path = dir.path().join("snippets.json");
+        fs::write(&path, content).unwrap();
+        assert!(Store::new(&path, None, 0).load_editable().is_ok());
+
+        let path = dir.path().join("greet.code-snippets");
+        fs::write(&path, content).unwrap();
+        assert!(Store::new(&path, None, 0).load_editable().is_err());
+    }
+
+    #[test]
+    fn new_files_are_indented() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snippets.json");
+        let mut store = Store::new(&path, None, 0);
+        store
+            .save(&mut Default::default(), "a".to_owned(), Snippet::new("b"), None)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\n    \"a\": \"b\"\n}\n");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{snippet::Snippet, Snippets};
+use indexmap::IndexMap;
+use serde::{de::IgnoredAny, Deserialize};
+
+/// A snippet of a VS Code snippet file, its prefixes are the names it's typed as in the
+/// editor and its scope the languages it is used for.
+#[derive(Deserialize)]
+struct CodeSnippet {
+    body: Lines,
+    #[serde(default)]
+    prefix: Option<Lines>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+    /// Any other keys, snippset metadata among them means it's a snippset file instead.
+    #[serde(flatten)]
+    rest: IndexMap<String, IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Lines {
+    Line(String),
+    Lines(Vec<String>),
+}
+
+impl Lines {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Lines::Line(line) => vec![line],
+            Lines::Lines(lines) => lines,
+        }
+    }
+}
+
+/// Reads the snippets of a VS Code snippet file, where each snippet is an object with a
+/// `body` written as a string or an array of lines. Files without the `.code-snippets`
+/// extension are only taken as VS Code ones when their snippets have a `prefix` or a
+/// `scope`, anything else returns `None` so it can be read as a snippset file.
+pub fn parse(content: &str, code_snippets: bool) -> Option<Snippets> {
+    let snippets: IndexMap<String, CodeSnippet> =
+        serde_json::from_str(&strip_comments(content)).ok()?;
+    let snippset_only = ["tags", "language", "aliases"];
+    let metadata = snippets
+        .values()
+        .any(|s| s.rest.keys().any(|k| snippset_only.contains(&k.as_str())));
+    let vscode_only = snippets
+        .values()
+        .any(|s| s.prefix.is_some() || s.scope.is_some());
+    if snippets.is_empty() || metadata || !(code_snippets || vscode_only) {
+        return None;
+    }
+
+    let snippets = snippets
+        .into_iter()
+        .map(|(title, snippet)| {
+            let snippet = Snippet {
+                body: snippet.body.into_vec().join("\n"),
+                description: snippet.description,
+                tags: Vec::new(),
+                // Only the first language of a scope such as `javascript,typescript`
+                language: snippet
+                    .scope
+                    .and_then(|s| s.split(',').next().map(|l| l.trim().to_owned()))
+                    .filter(|l| !l.is_empty()),
+                aliases: snippet.prefix.map(Lines::into_vec).unwrap_or_default(),
+            };
+            (title, snippet)
+        })
+        .collect();
+    Some(snippets)
+}
+
+/// VS Code snippet files may contain comments and trailing commas, those are removed so
+/// the rest can be read as JSON.
+fn strip_comments(content: &str) -> String {
+    let chars = content.chars().collect::<Vec<char>>();
+    let mut json = String::new();
+    let mut in_string = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match &chars[i..] {
+            ['\\', c, ..] if in_string => {
+                json.extend(['\\', *c]);
+                i += 2;
+                continue;
+            }
+            ['"', ..] => in_string = !in_string,
+            ['/', '/', ..] if !in_string => {
+                i += chars[i..].iter().take_while(|c| **c != '\n').count();
+                continue;
+            }
+            ['/', '*', rest @ ..] if !in_string => {
+                let end = (0..rest.len()).find(|j| rest[*j..].starts_with(&['*', '/']));
+                i += end.map_or(chars.len(), |end| end + 4);
+                continue;
+            }
+            [',', ..] if !in_string => {
+                let next = json_after(&chars[i + 1..]);
+                if next == Some('}') || next == Some(']') {
+                    i += 1;
+                    continue;
+                }
+            }
+            _ => (),
+        }
+
+        json.push(chars[i]);
+        i += 1;
+    }
+
+    json
+}
+
+/// The next character which is not whitespace or part of a comment.
+fn json_after(chars: &[char]) -> Option<char> {
+    let mut i = 0;
+    loop {
+        match chars.get(i..)? {
+            ['/', '/', ..] => i += chars[i..].iter().take_while(|c| **c != '\n').count(),
+            ['/', '*', rest @ ..] => {
+                let end = (0..rest.len()).find(|j| rest[*j..].starts_with(&['*', '/']))?;
+                i += end + 4;
+            }
+            [c, ..] if c.is_whitespace() => i += 1,
+            [c, ..] => return Some(*c),
+            [] => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn reads_string_and_array_bodies() {
+        let snippets = parse(
+            r#"{
+                "Print": { "prefix": "log", "body": "console.log($1);", "description": "Log" },
+                "For": { "prefix": ["for"], "body": ["for ${1:i} {", "\t$0", "}"], "scope": "rust,toml" }
+            }"#,
+            false,
+        )
+        .unwrap();
+        assert_eq!(snippets["Print"].body, "console.log($1);");
+        assert_eq!(snippets["Print"].aliases, vec!["log"]);
+        assert_eq!(snippets["Print"].description, Some("Log".to_owned()));
+        assert_eq!(snippets["For"].body, "for ${1:i} {\n\t$0\n}");
+        assert_eq!(snippets["For"].language, Some("rust".to_owned()));
+    }
+
+    #[test]
+    fn ignores_comments_and_trailing_commas() {
+        let snippets = parse(
+            r#"{
+                // Place your snippets here
+                "Url": { "body": "http://${1:host}/* not a comment */", },
+                /* "Old": { "body": "old" }, */
+            }"#,
+            true,
+        )
+        .unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets["Url"].body, "http://${1:host}/* not a comment */");
+    }
+
+    #[test]
+    fn keeps_escaped_quotes() {
+        let snippets = parse(r#"{ "Quote": { "body": "say \"//hi\"" } }"#, true).unwrap();
+        assert_eq!(snippets["Quote"].body, "say \"//hi\"");
+    }
+
+    #[test]
+    fn rejects_snippset_files() {
+        assert!(parse(r#"{ "Title": "Snippet {}" }"#, false).is_none());
+        assert!(parse("{}", true).is_none());
+        assert!(parse(r#"{ "Title": { "body": "ls", "tags": ["fs"] } }"#, false).is_none());
+        let described = r#"{"greet": {"body": "Hello {name}", "description": "Greeting"}}"#;
+        assert!(parse(described, false).is_none());
+        assert!(parse(described, true).is_some());
+    }
+}
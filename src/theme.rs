@@ -0,0 +1,28 @@
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+/// Colors of the snippet editor, written such as `yellow`, `light-blue` or `#ff8800`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub placeholder: Color,
+    /// Values not matching their type and problems shown below the snippet.
+    pub invalid: Color,
+    /// Keys listed at the bottom of the editor.
+    pub keys: Color,
+    pub cursor: Color,
+    /// Background of a default or a choice while it's active.
+    pub selection: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            placeholder: Color::Yellow,
+            invalid: Color::Red,
+            keys: Color::Blue,
+            cursor: Color::White,
+            selection: Color::DarkGray,
+        }
+    }
+}